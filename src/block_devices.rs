@@ -0,0 +1,250 @@
+use super::Result;
+
+/// A disk or partition from `/sys/block`
+#[derive(Debug, PartialEq)]
+pub struct BlockDevice {
+    /// Name as used in `/proc/diskstats`, such as `sda` or `dm-0`
+    pub name: String,
+    /// Name of the disk a partition is part of, `None` for whole disks
+    pub parent: Option<String>,
+    /// Whether this is a whole disk backed by hardware, as opposed to a partition or a virtual
+    /// device such as a loop, ram, zram or device mapper device
+    pub physical: bool,
+    /// Whether the disk is spinning, partitions report the value of their disk
+    pub rotational: bool,
+    /// Whether the media can be removed, such as a CD, partitions report the value of their disk
+    pub removable: bool,
+    /// Size in bytes
+    pub size: u64,
+    /// Smallest unit the device can address in bytes, partitions report the value of their disk
+    pub logical_block_size: u64,
+    /// Model as reported by the hardware, partitions report the value of their disk
+    pub model: Option<String>,
+    /// Name of a device mapper device, such as the LVM volume `vg0-root` for `dm-0`
+    pub device_mapper_name: Option<String>,
+}
+
+impl BlockDevice {
+    pub fn is_partition(&self) -> bool {
+        self.parent.is_some()
+    }
+}
+
+/// Read all disks and their partitions.
+#[cfg(target_os = "linux")]
+pub fn read() -> Result<Vec<BlockDevice>> {
+    os::read()
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) mod os {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use super::super::{
+        file_to_string, path_to_string, read_file_value_as_u64, ProbeError, Result,
+    };
+    use super::BlockDevice;
+
+    #[inline]
+    pub fn read() -> Result<Vec<BlockDevice>> {
+        read_and_parse_block_devices(Path::new("/sys/block"))
+    }
+
+    pub fn read_and_parse_block_devices(path: &Path) -> Result<Vec<BlockDevice>> {
+        let mut out = Vec::new();
+        for (name, disk_path) in sorted_dir_entries(path)? {
+            let disk = read_disk(&disk_path, name)?;
+
+            // Partitions are subdirectories of their disk with a `partition` file
+            let mut partitions = Vec::new();
+            for (name, partition_path) in sorted_dir_entries(&disk_path)? {
+                if partition_path.join("partition").exists() {
+                    partitions.push(read_partition(&partition_path, name, &disk)?);
+                }
+            }
+
+            out.push(disk);
+            out.append(&mut partitions);
+        }
+
+        Ok(out)
+    }
+
+    fn read_disk(path: &Path, name: String) -> Result<BlockDevice> {
+        Ok(BlockDevice {
+            name,
+            parent: None,
+            // Virtual devices are not linked to the device they run on
+            physical: path.join("device").exists(),
+            rotational: read_flag(&path.join("queue/rotational"))?,
+            removable: read_flag(&path.join("removable"))?,
+            size: read_size(path)?,
+            logical_block_size: read_file_value_as_u64(&path.join("queue/logical_block_size"))?,
+            model: read_optional_string(&path.join("device/model"))?,
+            device_mapper_name: read_optional_string(&path.join("dm/name"))?,
+        })
+    }
+
+    fn read_partition(path: &Path, name: String, disk: &BlockDevice) -> Result<BlockDevice> {
+        Ok(BlockDevice {
+            name,
+            parent: Some(disk.name.to_owned()),
+            physical: false,
+            rotational: disk.rotational,
+            removable: disk.removable,
+            size: read_size(path)?,
+            logical_block_size: disk.logical_block_size,
+            model: disk.model.to_owned(),
+            device_mapper_name: None,
+        })
+    }
+
+    #[inline]
+    fn sorted_dir_entries(path: &Path) -> Result<Vec<(String, PathBuf)>> {
+        let entries = fs::read_dir(path).map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
+
+        let mut out = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
+            if !entry.path().is_dir() {
+                continue;
+            }
+            if let Some(name) = entry.file_name().to_str() {
+                out.push((name.to_owned(), entry.path()));
+            }
+        }
+        out.sort();
+
+        Ok(out)
+    }
+
+    /// The size is reported in sectors of 512 bytes, regardless of the block size of the device
+    #[inline]
+    fn read_size(path: &Path) -> Result<u64> {
+        Ok(read_file_value_as_u64(&path.join("size"))? * 512)
+    }
+
+    #[inline]
+    fn read_flag(path: &Path) -> Result<bool> {
+        match read_file_value_as_u64(path)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            value => Err(ProbeError::UnexpectedContent(format!(
+                "Unexpected value {} in '{}'",
+                value,
+                path_to_string(path)
+            ))),
+        }
+    }
+
+    #[inline]
+    fn read_optional_string(path: &Path) -> Result<Option<String>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let value = file_to_string(path)?;
+        match value.trim() {
+            "" => Ok(None),
+            value => Ok(Some(value.to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::super::ProbeError;
+    use super::os::read_and_parse_block_devices;
+    use super::BlockDevice;
+    use std::path::Path;
+
+    #[test]
+    fn test_read_block_devices() {
+        assert!(super::read().is_ok());
+    }
+
+    #[test]
+    fn test_read_and_parse_block_devices() {
+        let devices = read_and_parse_block_devices(Path::new("fixtures/linux/sys/block")).unwrap();
+
+        let names: Vec<&str> = devices.iter().map(|device| device.name.as_str()).collect();
+        assert_eq!(vec!["dm-0", "loop0", "sda", "sda1", "sda2", "sdb"], names);
+
+        assert_eq!(
+            BlockDevice {
+                name: "dm-0".to_owned(),
+                parent: None,
+                physical: false,
+                rotational: false,
+                removable: false,
+                size: 53687091200,
+                logical_block_size: 512,
+                model: None,
+                device_mapper_name: Some("vg0-root".to_owned()),
+            },
+            devices[0]
+        );
+        assert!(!devices[1].physical);
+        assert_eq!(
+            BlockDevice {
+                name: "sda".to_owned(),
+                parent: None,
+                physical: true,
+                rotational: false,
+                removable: false,
+                size: 1000204886016,
+                logical_block_size: 512,
+                model: Some("Samsung SSD 860".to_owned()),
+                device_mapper_name: None,
+            },
+            devices[2]
+        );
+        assert_eq!(
+            BlockDevice {
+                name: "sda1".to_owned(),
+                parent: Some("sda".to_owned()),
+                physical: false,
+                rotational: false,
+                removable: false,
+                size: 536870912,
+                logical_block_size: 512,
+                model: Some("Samsung SSD 860".to_owned()),
+                device_mapper_name: None,
+            },
+            devices[3]
+        );
+        assert!(devices[3].is_partition());
+        assert!(!devices[2].is_partition());
+        assert_eq!(
+            BlockDevice {
+                name: "sdb".to_owned(),
+                parent: None,
+                physical: true,
+                rotational: true,
+                removable: true,
+                size: 30752000000,
+                logical_block_size: 4096,
+                model: Some("USB Flash Drive".to_owned()),
+                device_mapper_name: None,
+            },
+            devices[5]
+        );
+    }
+
+    #[test]
+    fn test_read_and_parse_block_devices_wrong_path() {
+        match read_and_parse_block_devices(Path::new("/nonsense")) {
+            Err(ProbeError::IO(_, _)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_block_devices_garbage() {
+        match read_and_parse_block_devices(Path::new("fixtures/linux/sys/block_garbage")) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,458 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::Path;
+
+use crate::error::ProbeError;
+use crate::{
+    calculate_time_difference, dir_exists, per_minute_value, per_minute_value_option, FieldKind,
+    Result,
+};
+use crate::{file_to_buf_reader, parse_u64, path_to_string};
+
+/// Measurement of the block I/O of the container at a certain time
+#[derive(Debug, PartialEq)]
+pub struct CgroupIoMeasurement {
+    pub precise_time_ns: u64,
+    pub devices: Vec<CgroupIoStat>,
+}
+
+impl CgroupIoMeasurement {
+    /// Calculate the block I/O per minute based on this measurement and a measurement in the
+    /// future. It is advisable to make the next measurement roughly a minute from this one for the
+    /// most reliable result.
+    pub fn calculate_per_minute(
+        &self,
+        next_measurement: &CgroupIoMeasurement,
+    ) -> Result<CgroupIoPerMinute> {
+        let time_difference =
+            calculate_time_difference(self.precise_time_ns, next_measurement.precise_time_ns)?;
+
+        let mut devices = Vec::new();
+
+        for stat in self.devices.iter() {
+            let next_stat = match next_measurement
+                .devices
+                .iter()
+                .find(|next| next.major == stat.major && next.minor == stat.minor)
+            {
+                Some(stat) => stat,
+                None => {
+                    return Err(ProbeError::UnexpectedContent(format!(
+                        "{}:{} is not present in the next measurement",
+                        stat.major, stat.minor
+                    )))
+                }
+            };
+
+            devices.push(CgroupIoStat {
+                major: next_stat.major,
+                minor: next_stat.minor,
+                device: next_stat.device.to_owned(),
+                read_bytes: per_minute_value(
+                    "read_bytes",
+                    FieldKind::Counter,
+                    next_stat.read_bytes,
+                    stat.read_bytes,
+                    time_difference,
+                )?,
+                write_bytes: per_minute_value(
+                    "write_bytes",
+                    FieldKind::Counter,
+                    next_stat.write_bytes,
+                    stat.write_bytes,
+                    time_difference,
+                )?,
+                reads: per_minute_value(
+                    "reads",
+                    FieldKind::Counter,
+                    next_stat.reads,
+                    stat.reads,
+                    time_difference,
+                )?,
+                writes: per_minute_value(
+                    "writes",
+                    FieldKind::Counter,
+                    next_stat.writes,
+                    stat.writes,
+                    time_difference,
+                )?,
+                discard_bytes: per_minute_value_option(
+                    "discard_bytes",
+                    FieldKind::Counter,
+                    next_stat.discard_bytes,
+                    stat.discard_bytes,
+                    time_difference,
+                )?,
+                discards: per_minute_value_option(
+                    "discards",
+                    FieldKind::Counter,
+                    next_stat.discards,
+                    stat.discards,
+                    time_difference,
+                )?,
+            });
+        }
+
+        Ok(CgroupIoPerMinute { devices })
+    }
+}
+
+/// Block I/O of the container on a single device
+#[derive(Debug, PartialEq)]
+pub struct CgroupIoStat {
+    pub major: u64,
+    pub minor: u64,
+    /// Name of the device as used in `/proc/diskstats`, `None` when it could not be found
+    pub device: Option<String>,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+    pub reads: u64,
+    pub writes: u64,
+    /// Only available on cgroups v2
+    pub discard_bytes: Option<u64>,
+    /// Only available on cgroups v2
+    pub discards: Option<u64>,
+}
+
+/// Block I/O of the container for a certain minute, calculated based on two measurements.
+#[derive(Debug, PartialEq)]
+pub struct CgroupIoPerMinute {
+    pub devices: Vec<CgroupIoStat>,
+}
+
+/// Block I/O limits of the container, per device and in total
+#[derive(Debug, PartialEq)]
+pub struct CgroupIoLimits {
+    /// Weight of the container relative to its siblings when they compete for I/O, `io.weight`
+    /// on cgroups v2 and `blkio.weight` on cgroups v1
+    pub default_weight: Option<u64>,
+    pub devices: Vec<CgroupIoLimit>,
+}
+
+impl CgroupIoLimits {
+    /// Find the limits of a device, to pair them with its `CgroupIoStat`
+    pub fn device(&self, major: u64, minor: u64) -> Option<&CgroupIoLimit> {
+        self.devices
+            .iter()
+            .find(|limit| limit.major == major && limit.minor == minor)
+    }
+}
+
+/// Block I/O limits of the container on a single device. Limits are `None` when not set.
+#[derive(Debug, PartialEq)]
+pub struct CgroupIoLimit {
+    pub major: u64,
+    pub minor: u64,
+    /// Name of the device as used in `/proc/diskstats`, `None` when it could not be found
+    pub device: Option<String>,
+    pub read_bytes_per_second: Option<u64>,
+    pub write_bytes_per_second: Option<u64>,
+    pub reads_per_second: Option<u64>,
+    pub writes_per_second: Option<u64>,
+    /// Weight on this device, overriding the default weight
+    pub weight: Option<u64>,
+}
+
+impl CgroupIoLimit {
+    pub(crate) fn unlimited(
+        major: u64,
+        minor: u64,
+        device_names: &HashMap<(u64, u64), String>,
+    ) -> CgroupIoLimit {
+        CgroupIoLimit {
+            major,
+            minor,
+            device: device_names.get(&(major, minor)).cloned(),
+            read_bytes_per_second: None,
+            write_bytes_per_second: None,
+            reads_per_second: None,
+            writes_per_second: None,
+            weight: None,
+        }
+    }
+}
+
+/// Read the current block I/O of the container per device. Devices are reported without a name
+/// when `/proc/diskstats` can't be read, which is common in containers.
+#[cfg(target_os = "linux")]
+pub fn read() -> Result<CgroupIoMeasurement> {
+    use super::cgroup_v1::read_and_parse_v1_sys_io;
+    use super::cgroup_v2::read_and_parse_v2_sys_io;
+
+    let io_cgroup = find_io_cgroup()?;
+    let device_names = read_device_names().unwrap_or_default();
+
+    match io_cgroup {
+        IoCgroup::V1(dir) => read_and_parse_v1_sys_io(dir, &device_names),
+        IoCgroup::V2(dir) => read_and_parse_v2_sys_io(dir, &device_names),
+    }
+}
+
+/// Read the block I/O limits of the container, such as the maximum bytes per second per device.
+/// Devices are reported without a name when `/proc/diskstats` can't be read.
+#[cfg(target_os = "linux")]
+pub fn read_limits() -> Result<CgroupIoLimits> {
+    use super::cgroup_v1::read_and_parse_v1_sys_io_limits;
+    use super::cgroup_v2::read_and_parse_v2_sys_io_limits;
+
+    let io_cgroup = find_io_cgroup()?;
+    let device_names = read_device_names().unwrap_or_default();
+
+    match io_cgroup {
+        IoCgroup::V1(dir) => read_and_parse_v1_sys_io_limits(dir, &device_names),
+        IoCgroup::V2(dir) => read_and_parse_v2_sys_io_limits(dir, &device_names),
+    }
+}
+
+/// Directory with the block I/O files of the container for the cgroups version in use
+#[cfg(target_os = "linux")]
+enum IoCgroup {
+    V1(&'static Path),
+    V2(&'static Path),
+}
+
+#[cfg(target_os = "linux")]
+fn find_io_cgroup() -> Result<IoCgroup> {
+    let v2_sys_fs_dir = Path::new("/sys/fs/cgroup");
+    let v2_sys_fs_file = v2_sys_fs_dir.join("io.stat");
+
+    if v2_sys_fs_file.exists() {
+        return Ok(IoCgroup::V2(v2_sys_fs_dir));
+    }
+
+    let v1_sys_fs_dir = Path::new("/sys/fs/cgroup/blkio/");
+    if dir_exists(v1_sys_fs_dir) {
+        return Ok(IoCgroup::V1(v1_sys_fs_dir));
+    }
+
+    let message = format!(
+        "Directory `{}` and file `{}` not found",
+        v1_sys_fs_dir.to_str().unwrap_or("unknown path"),
+        v2_sys_fs_file.to_str().unwrap_or("unknown path")
+    );
+    Err(ProbeError::UnexpectedContent(message))
+}
+
+#[cfg(target_os = "linux")]
+#[inline]
+fn read_device_names() -> Result<HashMap<(u64, u64), String>> {
+    crate::disk_stats::read().map(|measurement| measurement.device_names())
+}
+
+/// Parse a device number such as `8:16` into its major and minor number
+#[inline]
+pub(crate) fn parse_device_number(segment: &str) -> Result<(u64, u64)> {
+    let mut numbers = segment.splitn(2, ':');
+    match (numbers.next(), numbers.next()) {
+        (Some(major), Some(minor)) => Ok((parse_u64(major)?, parse_u64(minor)?)),
+        _ => Err(ProbeError::UnexpectedContent(format!(
+            "Could not parse device '{}'",
+            segment
+        ))),
+    }
+}
+
+/// Values per device, by major and minor number
+pub(crate) type DeviceValues = Vec<((u64, u64), u64)>;
+
+/// Read the first of the weight files which exists, such as `io.weight` or `io.bfq.weight`
+/// depending on the I/O scheduler. These contain a default weight, either as `default 100` or as
+/// a plain number, and weights per device such as `8:16 200`.
+pub(crate) fn read_and_parse_weight_files(paths: &[&Path]) -> Result<(Option<u64>, DeviceValues)> {
+    let path = match paths.iter().find(|path| path.exists()) {
+        Some(path) => path,
+        None => return Ok((None, Vec::new())),
+    };
+    let reader = file_to_buf_reader(path)?;
+
+    let mut default_weight = None;
+    let mut device_weights = Vec::new();
+    for line in reader.lines() {
+        let line = line.map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
+        let segments: Vec<&str> = line.split_whitespace().collect();
+        match segments.as_slice() {
+            [] => (),
+            [weight] | ["default", weight] => default_weight = Some(parse_u64(weight)?),
+            [device, weight] => {
+                device_weights.push((parse_device_number(device)?, parse_u64(weight)?))
+            }
+            _ => {
+                return Err(ProbeError::UnexpectedContent(format!(
+                    "Incorrect number of segments in '{}'",
+                    line
+                )))
+            }
+        }
+    }
+
+    Ok((default_weight, device_weights))
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::{CgroupIoLimit, CgroupIoLimits, CgroupIoMeasurement, CgroupIoStat};
+    use crate::ProbeError;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_read_from_container() {
+        assert!(super::read().is_ok());
+    }
+
+    #[test]
+    fn test_read_limits_from_container() {
+        assert!(super::read_limits().is_ok());
+    }
+
+    #[test]
+    fn test_limits_device() {
+        let mut limit = CgroupIoLimit::unlimited(8, 16, &HashMap::new());
+        limit.reads_per_second = Some(100);
+        let limits = CgroupIoLimits {
+            default_weight: None,
+            devices: vec![limit],
+        };
+
+        assert_eq!(Some(100), limits.device(8, 16).unwrap().reads_per_second);
+        assert_eq!(None, limits.device(8, 0));
+    }
+
+    #[test]
+    fn test_parse_device_number() {
+        assert_eq!((253, 0), super::parse_device_number("253:0").unwrap());
+
+        match super::parse_device_number("Total") {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_full_minute() {
+        let measurement1 = CgroupIoMeasurement {
+            precise_time_ns: 60_000_000_000,
+            devices: vec![helpers::stat(8, 16, 100), helpers::stat(253, 0, 100)],
+        };
+        let measurement2 = CgroupIoMeasurement {
+            precise_time_ns: 120_000_000_000,
+            devices: vec![helpers::stat(253, 0, 160), helpers::stat(8, 16, 220)],
+        };
+
+        let per_minute = measurement1.calculate_per_minute(&measurement2).unwrap();
+        assert_eq!(
+            vec![helpers::stat(8, 16, 120), helpers::stat(253, 0, 60)],
+            per_minute.devices
+        );
+    }
+
+    #[test]
+    fn test_calculate_per_minute_partial_minute() {
+        let measurement1 = CgroupIoMeasurement {
+            precise_time_ns: 60_000_000_000,
+            devices: vec![helpers::stat(8, 16, 100)],
+        };
+        let measurement2 = CgroupIoMeasurement {
+            precise_time_ns: 90_000_000_000,
+            devices: vec![helpers::stat(8, 16, 220)],
+        };
+
+        let per_minute = measurement1.calculate_per_minute(&measurement2).unwrap();
+        assert_eq!(vec![helpers::stat(8, 16, 240)], per_minute.devices);
+    }
+
+    #[test]
+    fn test_calculate_per_minute_without_discards() {
+        let measurement1 = CgroupIoMeasurement {
+            precise_time_ns: 60_000_000_000,
+            devices: vec![helpers::stat_without_discards(8, 16, 100)],
+        };
+        let measurement2 = CgroupIoMeasurement {
+            precise_time_ns: 120_000_000_000,
+            devices: vec![helpers::stat_without_discards(8, 16, 220)],
+        };
+
+        let per_minute = measurement1.calculate_per_minute(&measurement2).unwrap();
+        assert_eq!(
+            vec![helpers::stat_without_discards(8, 16, 120)],
+            per_minute.devices
+        );
+    }
+
+    #[test]
+    fn test_calculate_per_minute_wrong_times() {
+        let measurement1 = CgroupIoMeasurement {
+            precise_time_ns: 90_000_000_000,
+            devices: Vec::new(),
+        };
+        let measurement2 = CgroupIoMeasurement {
+            precise_time_ns: 60_000_000_000,
+            devices: Vec::new(),
+        };
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::InvalidInput(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_values_lower() {
+        let measurement1 = CgroupIoMeasurement {
+            precise_time_ns: 60_000_000_000,
+            devices: vec![helpers::stat(8, 16, 220)],
+        };
+        let measurement2 = CgroupIoMeasurement {
+            precise_time_ns: 120_000_000_000,
+            devices: vec![helpers::stat(8, 16, 100)],
+        };
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_missing_device() {
+        let measurement1 = CgroupIoMeasurement {
+            precise_time_ns: 60_000_000_000,
+            devices: vec![helpers::stat(8, 16, 100)],
+        };
+        let measurement2 = CgroupIoMeasurement {
+            precise_time_ns: 120_000_000_000,
+            devices: vec![helpers::stat(8, 0, 220)],
+        };
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    mod helpers {
+        use super::CgroupIoStat;
+
+        pub fn stat(major: u64, minor: u64, value: u64) -> CgroupIoStat {
+            CgroupIoStat {
+                major,
+                minor,
+                device: Some(format!("dev{}", minor)),
+                read_bytes: value,
+                write_bytes: value,
+                reads: value,
+                writes: value,
+                discard_bytes: Some(value),
+                discards: Some(value),
+            }
+        }
+
+        pub fn stat_without_discards(major: u64, minor: u64, value: u64) -> CgroupIoStat {
+            CgroupIoStat {
+                discard_bytes: None,
+                discards: None,
+                ..stat(major, minor, value)
+            }
+        }
+    }
+}
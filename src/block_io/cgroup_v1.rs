@@ -0,0 +1,290 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::Path;
+
+use super::cgroup::{
+    parse_device_number, read_and_parse_weight_files, CgroupIoLimit, CgroupIoLimits,
+    CgroupIoMeasurement, CgroupIoStat, DeviceValues,
+};
+use crate::{file_to_buf_reader, parse_u64, path_to_string, precise_time_ns};
+use crate::{ProbeError, Result};
+
+#[cfg(target_os = "linux")]
+pub fn read_and_parse_v1_sys_io(
+    path: &Path,
+    device_names: &HashMap<(u64, u64), String>,
+) -> Result<CgroupIoMeasurement> {
+    let time = precise_time_ns();
+    // The throttle files count all I/O of the cgroup, regardless of the I/O scheduler in use
+    let bytes = read_and_parse_v1_io_file(&path.join("blkio.throttle.io_service_bytes"))?;
+    let operations = read_and_parse_v1_io_file(&path.join("blkio.throttle.io_serviced"))?;
+
+    let mut devices = Vec::new();
+    for (&(major, minor), (read_bytes, write_bytes)) in bytes.iter() {
+        let (reads, writes) = operations.get(&(major, minor)).ok_or_else(|| {
+            ProbeError::UnexpectedContent(format!(
+                "Device {}:{} not found in blkio.throttle.io_serviced",
+                major, minor
+            ))
+        })?;
+
+        devices.push(CgroupIoStat {
+            major,
+            minor,
+            device: device_names.get(&(major, minor)).cloned(),
+            read_bytes: *read_bytes,
+            write_bytes: *write_bytes,
+            reads: *reads,
+            writes: *writes,
+            discard_bytes: None,
+            discards: None,
+        });
+    }
+
+    Ok(CgroupIoMeasurement {
+        precise_time_ns: time,
+        devices,
+    })
+}
+
+#[cfg(target_os = "linux")]
+pub fn read_and_parse_v1_sys_io_limits(
+    path: &Path,
+    device_names: &HashMap<(u64, u64), String>,
+) -> Result<CgroupIoLimits> {
+    let mut limits = BTreeMap::new();
+
+    let throttle_files = [
+        "blkio.throttle.read_bps_device",
+        "blkio.throttle.write_bps_device",
+        "blkio.throttle.read_iops_device",
+        "blkio.throttle.write_iops_device",
+    ];
+    for file in throttle_files.iter() {
+        for ((major, minor), value) in read_and_parse_v1_throttle_file(&path.join(file))? {
+            let limit = limits
+                .entry((major, minor))
+                .or_insert_with(|| CgroupIoLimit::unlimited(major, minor, device_names));
+            match *file {
+                "blkio.throttle.read_bps_device" => limit.read_bytes_per_second = Some(value),
+                "blkio.throttle.write_bps_device" => limit.write_bytes_per_second = Some(value),
+                "blkio.throttle.read_iops_device" => limit.reads_per_second = Some(value),
+                _ => limit.writes_per_second = Some(value),
+            }
+        }
+    }
+
+    // The BFQ I/O scheduler uses its own weights
+    let (default_weight, _) =
+        read_and_parse_weight_files(&[&path.join("blkio.weight"), &path.join("blkio.bfq.weight")])?;
+    let (_, device_weights) = read_and_parse_weight_files(&[
+        &path.join("blkio.weight_device"),
+        &path.join("blkio.bfq.weight_device"),
+    ])?;
+    for ((major, minor), weight) in device_weights {
+        limits
+            .entry((major, minor))
+            .or_insert_with(|| CgroupIoLimit::unlimited(major, minor, device_names))
+            .weight = Some(weight);
+    }
+
+    Ok(CgroupIoLimits {
+        default_weight,
+        devices: limits.into_values().collect(),
+    })
+}
+
+/// Read a throttle file, which only lists the devices with a limit such as `8:16 1048576`
+#[inline]
+fn read_and_parse_v1_throttle_file(path: &Path) -> Result<DeviceValues> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let reader = file_to_buf_reader(path)?;
+
+    let mut out = Vec::new();
+    for line in reader.lines() {
+        let line = line.map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
+        let segments: Vec<&str> = line.split_whitespace().collect();
+        match segments.len() {
+            0 => continue,
+            2 => out.push((parse_device_number(segments[0])?, parse_u64(segments[1])?)),
+            _ => {
+                return Err(ProbeError::UnexpectedContent(format!(
+                    "Incorrect number of segments in '{}'",
+                    line
+                )))
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/// Read the `Read` and `Write` values per device from a blkio file. Other operations such as
+/// `Sync` and `Total` overlap with these and are skipped.
+#[inline]
+fn read_and_parse_v1_io_file(path: &Path) -> Result<BTreeMap<(u64, u64), (u64, u64)>> {
+    let reader = file_to_buf_reader(path)?;
+
+    let mut out = BTreeMap::new();
+    for line in reader.lines() {
+        let line = line.map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
+        let segments: Vec<&str> = line.split_whitespace().collect();
+        match segments.len() {
+            // The total of all devices, such as `Total 0`
+            0 | 2 => continue,
+            3 => (),
+            _ => {
+                return Err(ProbeError::UnexpectedContent(format!(
+                    "Incorrect number of segments in '{}'",
+                    line
+                )))
+            }
+        }
+
+        let device = parse_device_number(segments[0])?;
+        let value = parse_u64(segments[2])?;
+        let entry = out.entry(device).or_insert((0, 0));
+        match segments[1] {
+            "Read" => entry.0 = value,
+            "Write" => entry.1 = value,
+            _ => (),
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::{read_and_parse_v1_sys_io, read_and_parse_v1_sys_io_limits};
+    use crate::block_io::cgroup::{CgroupIoLimit, CgroupIoLimits, CgroupIoStat};
+    use crate::ProbeError;
+    use std::path::Path;
+
+    #[test]
+    fn test_read_and_parse_v1_sys_io() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/blkio/");
+        let measurement = read_and_parse_v1_sys_io(path, &helpers::device_names()).unwrap();
+
+        assert!(measurement.precise_time_ns > 0);
+        assert_eq!(
+            vec![
+                CgroupIoStat {
+                    major: 8,
+                    minor: 16,
+                    device: Some("sdb".to_owned()),
+                    read_bytes: 1459200,
+                    write_bytes: 314773504,
+                    reads: 192,
+                    writes: 353,
+                    discard_bytes: None,
+                    discards: None,
+                },
+                CgroupIoStat {
+                    major: 253,
+                    minor: 0,
+                    device: None,
+                    read_bytes: 8192,
+                    write_bytes: 0,
+                    reads: 2,
+                    writes: 0,
+                    discard_bytes: None,
+                    discards: None,
+                },
+            ],
+            measurement.devices
+        );
+    }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_io_idle() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/blkio_idle/");
+        let measurement = read_and_parse_v1_sys_io(path, &helpers::device_names()).unwrap();
+        assert!(measurement.devices.is_empty());
+    }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_io_wrong_path() {
+        let path = Path::new("/nonsense");
+        match read_and_parse_v1_sys_io(path, &helpers::device_names()) {
+            Err(ProbeError::IO(_, _)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_io_garbage() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/blkio_garbage/");
+        match read_and_parse_v1_sys_io(path, &helpers::device_names()) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_io_limits() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/blkio/");
+        let limits = read_and_parse_v1_sys_io_limits(path, &helpers::device_names()).unwrap();
+
+        assert_eq!(
+            CgroupIoLimits {
+                default_weight: Some(500),
+                devices: vec![
+                    CgroupIoLimit {
+                        major: 8,
+                        minor: 16,
+                        device: Some("sdb".to_owned()),
+                        read_bytes_per_second: Some(2097152),
+                        write_bytes_per_second: None,
+                        reads_per_second: None,
+                        writes_per_second: Some(120),
+                        weight: Some(800),
+                    },
+                    CgroupIoLimit {
+                        major: 253,
+                        minor: 0,
+                        device: None,
+                        read_bytes_per_second: None,
+                        write_bytes_per_second: None,
+                        reads_per_second: None,
+                        writes_per_second: Some(50),
+                        weight: None,
+                    },
+                ],
+            },
+            limits
+        );
+    }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_io_limits_not_set() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/blkio_idle/");
+        let limits = read_and_parse_v1_sys_io_limits(path, &helpers::device_names()).unwrap();
+
+        assert_eq!(None, limits.default_weight);
+        assert!(limits.devices.is_empty());
+    }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_io_limits_garbage() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/blkio_garbage/");
+        match read_and_parse_v1_sys_io_limits(path, &helpers::device_names()) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    mod helpers {
+        use std::collections::HashMap;
+
+        pub fn device_names() -> HashMap<(u64, u64), String> {
+            let mut names = HashMap::new();
+            names.insert((8, 16), "sdb".to_owned());
+            names
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::BufRead;
+use std::path::Path;
+
+use super::cgroup::{
+    parse_device_number, read_and_parse_weight_files, CgroupIoLimit, CgroupIoLimits,
+    CgroupIoMeasurement, CgroupIoStat,
+};
+use crate::{file_to_buf_reader, parse_u64, path_to_string, precise_time_ns};
+use crate::{ProbeError, Result};
+
+#[cfg(target_os = "linux")]
+pub fn read_and_parse_v2_sys_io(
+    path: &Path,
+    device_names: &HashMap<(u64, u64), String>,
+) -> Result<CgroupIoMeasurement> {
+    let time = precise_time_ns();
+    let io_stat_path = path.join("io.stat");
+    let reader = file_to_buf_reader(&io_stat_path)?;
+
+    let mut devices = Vec::new();
+    for line in reader.lines() {
+        let line = line.map_err(|e| ProbeError::IO(e, path_to_string(&io_stat_path)))?;
+        let mut segments = line.split_whitespace();
+        let (major, minor) = match segments.next() {
+            Some(segment) => parse_device_number(segment)?,
+            None => continue,
+        };
+
+        // Keys such as `cost.usage` are reported when the io cost controller is enabled
+        let mut values = HashMap::new();
+        for segment in segments {
+            let mut key_value = segment.splitn(2, '=');
+            if let (Some(key), Some(value)) = (key_value.next(), key_value.next()) {
+                values.insert(key, value);
+            }
+        }
+        let required = |key: &str| match values.get(key) {
+            Some(value) => parse_u64(value),
+            None => Err(ProbeError::UnexpectedContent(format!(
+                "{} not found for device {}:{}",
+                key, major, minor
+            ))),
+        };
+        // Discards are reported since kernel 5.0
+        let optional = |key: &str| values.get(key).map(|value| parse_u64(value)).transpose();
+
+        devices.push(CgroupIoStat {
+            major,
+            minor,
+            device: device_names.get(&(major, minor)).cloned(),
+            read_bytes: required("rbytes")?,
+            write_bytes: required("wbytes")?,
+            reads: required("rios")?,
+            writes: required("wios")?,
+            discard_bytes: optional("dbytes")?,
+            discards: optional("dios")?,
+        });
+    }
+    devices.sort_by_key(|stat| (stat.major, stat.minor));
+
+    Ok(CgroupIoMeasurement {
+        precise_time_ns: time,
+        devices,
+    })
+}
+
+#[cfg(target_os = "linux")]
+pub fn read_and_parse_v2_sys_io_limits(
+    path: &Path,
+    device_names: &HashMap<(u64, u64), String>,
+) -> Result<CgroupIoLimits> {
+    let mut limits = BTreeMap::new();
+
+    // Not available in the root cgroup
+    let io_max_path = path.join("io.max");
+    if io_max_path.exists() {
+        let reader = file_to_buf_reader(&io_max_path)?;
+        for line in reader.lines() {
+            let line = line.map_err(|e| ProbeError::IO(e, path_to_string(&io_max_path)))?;
+            let mut segments = line.split_whitespace();
+            let (major, minor) = match segments.next() {
+                Some(segment) => parse_device_number(segment)?,
+                None => continue,
+            };
+            let limit = limits
+                .entry((major, minor))
+                .or_insert_with(|| CgroupIoLimit::unlimited(major, minor, device_names));
+
+            for segment in segments {
+                let mut key_value = segment.splitn(2, '=');
+                let (key, value) = match (key_value.next(), key_value.next()) {
+                    (Some(key), Some("max")) => (key, None),
+                    (Some(key), Some(value)) => (key, Some(parse_u64(value)?)),
+                    _ => continue,
+                };
+                match key {
+                    "rbps" => limit.read_bytes_per_second = value,
+                    "wbps" => limit.write_bytes_per_second = value,
+                    "riops" => limit.reads_per_second = value,
+                    "wiops" => limit.writes_per_second = value,
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    // The BFQ I/O scheduler uses its own weights
+    let (default_weight, device_weights) =
+        read_and_parse_weight_files(&[&path.join("io.weight"), &path.join("io.bfq.weight")])?;
+    for ((major, minor), weight) in device_weights {
+        limits
+            .entry((major, minor))
+            .or_insert_with(|| CgroupIoLimit::unlimited(major, minor, device_names))
+            .weight = Some(weight);
+    }
+
+    Ok(CgroupIoLimits {
+        default_weight,
+        devices: limits.into_values().collect(),
+    })
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::{read_and_parse_v2_sys_io, read_and_parse_v2_sys_io_limits};
+    use crate::block_io::cgroup::{CgroupIoLimit, CgroupIoLimits, CgroupIoStat};
+    use crate::ProbeError;
+    use std::path::Path;
+
+    #[test]
+    fn test_read_and_parse_v2_sys_io() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/io/");
+        let measurement = read_and_parse_v2_sys_io(path, &helpers::device_names()).unwrap();
+
+        assert!(measurement.precise_time_ns > 0);
+        assert_eq!(
+            vec![
+                CgroupIoStat {
+                    major: 8,
+                    minor: 16,
+                    device: Some("sdb".to_owned()),
+                    read_bytes: 1459200,
+                    write_bytes: 314773504,
+                    reads: 192,
+                    writes: 353,
+                    discard_bytes: Some(4096),
+                    discards: Some(1),
+                },
+                CgroupIoStat {
+                    major: 253,
+                    minor: 0,
+                    device: None,
+                    read_bytes: 8192,
+                    write_bytes: 0,
+                    reads: 2,
+                    writes: 0,
+                    discard_bytes: Some(0),
+                    discards: Some(0),
+                },
+            ],
+            measurement.devices
+        );
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_io_without_discard() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/io_without_discard/");
+        let measurement = read_and_parse_v2_sys_io(path, &helpers::device_names()).unwrap();
+
+        assert_eq!(1, measurement.devices.len());
+        assert_eq!(192, measurement.devices[0].reads);
+        assert_eq!(None, measurement.devices[0].discard_bytes);
+        assert_eq!(None, measurement.devices[0].discards);
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_io_wrong_path() {
+        let path = Path::new("/nonsense");
+        match read_and_parse_v2_sys_io(path, &helpers::device_names()) {
+            Err(ProbeError::IO(_, _)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_io_garbage() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/io_garbage/");
+        match read_and_parse_v2_sys_io(path, &helpers::device_names()) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_io_limits() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/io/");
+        let limits = read_and_parse_v2_sys_io_limits(path, &helpers::device_names()).unwrap();
+
+        assert_eq!(
+            CgroupIoLimits {
+                default_weight: Some(100),
+                devices: vec![
+                    CgroupIoLimit {
+                        major: 8,
+                        minor: 16,
+                        device: Some("sdb".to_owned()),
+                        read_bytes_per_second: Some(2097152),
+                        write_bytes_per_second: None,
+                        reads_per_second: None,
+                        writes_per_second: Some(120),
+                        weight: Some(200),
+                    },
+                    CgroupIoLimit {
+                        major: 253,
+                        minor: 0,
+                        device: None,
+                        read_bytes_per_second: None,
+                        write_bytes_per_second: Some(1048576),
+                        reads_per_second: None,
+                        writes_per_second: None,
+                        weight: None,
+                    },
+                ],
+            },
+            limits
+        );
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_io_limits_not_set() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/io_without_discard/");
+        let limits = read_and_parse_v2_sys_io_limits(path, &helpers::device_names()).unwrap();
+
+        assert_eq!(None, limits.default_weight);
+        assert!(limits.devices.is_empty());
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_io_limits_garbage() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/io_garbage/");
+        match read_and_parse_v2_sys_io_limits(path, &helpers::device_names()) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    mod helpers {
+        use std::collections::HashMap;
+
+        pub fn device_names() -> HashMap<(u64, u64), String> {
+            let mut names = HashMap::new();
+            names.insert((8, 16), "sdb".to_owned());
+            names
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod cgroup;
+mod cgroup_v1;
+mod cgroup_v2;
@@ -1,5 +1,5 @@
 use crate::error::ProbeError;
-use crate::{calculate_time_difference, dir_exists, time_adjusted, Result};
+use crate::{calculate_time_difference, dir_exists, per_minute_value, FieldKind, Result};
 use std::path::Path;
 
 /// Measurement of cpu stats at a certain time
@@ -18,20 +18,23 @@ impl CgroupCpuMeasurement {
             calculate_time_difference(self.precise_time_ns, next_measurement.precise_time_ns)?;
 
         Ok(CgroupCpuStat {
-            total_usage: time_adjusted(
+            total_usage: per_minute_value(
                 "total_usage",
+                FieldKind::Counter,
                 next_measurement.stat.total_usage,
                 self.stat.total_usage,
                 time_difference,
             )?,
-            user: time_adjusted(
+            user: per_minute_value(
                 "user",
+                FieldKind::Counter,
                 next_measurement.stat.user,
                 self.stat.user,
                 time_difference,
             )?,
-            system: time_adjusted(
+            system: per_minute_value(
                 "system",
+                FieldKind::Counter,
                 next_measurement.stat.system,
                 self.stat.system,
                 time_difference,
@@ -89,19 +92,30 @@ pub fn read(cpu_count: Option<f64>) -> Result<CgroupCpuMeasurement> {
     use super::cgroup_v1::read_and_parse_v1_sys_stat;
     use super::cgroup_v2::read_and_parse_v2_sys_stat;
 
+    let online_path = Path::new("/sys/devices/system/cpu/online");
+
     let v2_sys_fs_file = Path::new("/sys/fs/cgroup/cpu.stat");
     if v2_sys_fs_file.exists() {
         let v2_sys_fs_cpu_max_file = Path::new("/sys/fs/cgroup/cpu.max");
-        return read_and_parse_v2_sys_stat(&v2_sys_fs_file, v2_sys_fs_cpu_max_file, cpu_count);
+        return read_and_parse_v2_sys_stat(
+            v2_sys_fs_file,
+            v2_sys_fs_cpu_max_file,
+            cpu_count,
+            default_cpu_count(
+                Path::new("/sys/fs/cgroup/cpuset.cpus.effective"),
+                online_path,
+            ),
+        );
     }
 
     let v1_sys_fs_dir = Path::new("/sys/fs/cgroup/cpuacct/");
     if dir_exists(v1_sys_fs_dir) {
         return read_and_parse_v1_sys_stat(
-            &v1_sys_fs_dir,
-            &Path::new("/sys/fs/cgroup/cpu/cpu.cfs_period_us"),
-            &Path::new("/sys/fs/cgroup/cpu/cpu.cfs_quota_us"),
+            v1_sys_fs_dir,
+            Path::new("/sys/fs/cgroup/cpu/cpu.cfs_period_us"),
+            Path::new("/sys/fs/cgroup/cpu/cpu.cfs_quota_us"),
             cpu_count,
+            default_cpu_count(Path::new("/sys/fs/cgroup/cpuset/cpuset.cpus"), online_path),
         );
     }
 
@@ -112,11 +126,26 @@ pub fn read(cpu_count: Option<f64>) -> Result<CgroupCpuMeasurement> {
     )))
 }
 
+/// Number of CPUs used when no CPU limit is configured for the cgroup: the CPUs in its cpuset, or
+/// all online CPUs of the system when the cpuset can't be read.
+#[cfg(target_os = "linux")]
+fn default_cpu_count(cpuset_path: &Path, online_path: &Path) -> Option<f64> {
+    use super::info::os::read_and_parse_online;
+
+    [cpuset_path, online_path]
+        .iter()
+        .filter(|path| path.exists())
+        .filter_map(|path| read_and_parse_online(path).ok())
+        .find(|cpus| !cpus.is_empty())
+        .map(|cpus| cpus.len() as f64)
+}
+
 #[cfg(test)]
 #[cfg(target_os = "linux")]
 mod test {
     use super::{CgroupCpuMeasurement, CgroupCpuStat};
     use crate::error::ProbeError;
+    use std::path::Path;
 
     #[test]
     fn test_read() {
@@ -124,6 +153,43 @@ mod test {
         assert!(super::read(Some(0.5)).is_ok());
     }
 
+    #[test]
+    fn test_default_cpu_count_v2_cpuset() {
+        let count = super::default_cpu_count(
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpuset.cpus.effective"),
+            Path::new("fixtures/linux/sys/devices/system/cpu/online"),
+        );
+        assert_eq!(Some(2.0), count);
+    }
+
+    #[test]
+    fn test_default_cpu_count_v1_cpuset() {
+        let count = super::default_cpu_count(
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuset/cpuset.cpus"),
+            Path::new("fixtures/linux/sys/devices/system/cpu/online"),
+        );
+        assert_eq!(Some(1.0), count);
+    }
+
+    #[test]
+    fn test_default_cpu_count_without_cpuset() {
+        let count = super::default_cpu_count(
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/does_not_exist"),
+            Path::new("fixtures/linux/sys/devices/system/cpu/online"),
+        );
+        // Falls back to the online CPUs
+        assert_eq!(Some(4.0), count);
+    }
+
+    #[test]
+    fn test_default_cpu_count_cpuset_garbage() {
+        let count = super::default_cpu_count(
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpuset.cpus.effective_garbage"),
+            Path::new("fixtures/linux/sys/devices/system/cpu/online"),
+        );
+        assert_eq!(Some(4.0), count);
+    }
+
     #[test]
     fn test_calculate_per_minute_wrong_times() {
         let measurement1 = CgroupCpuMeasurement {
@@ -187,8 +253,8 @@ mod test {
             precise_time_ns: 60_000_000_000,
             stat: CgroupCpuStat {
                 total_usage: 1_000_000_000,
-                user: 10000_000_000,
-                system: 12000_000_000,
+                user: 10_000_000_000,
+                system: 12_000_000_000,
             },
         };
 
@@ -196,8 +262,8 @@ mod test {
             precise_time_ns: 90_000_000_000,
             stat: CgroupCpuStat {
                 total_usage: 1_500_000_000,
-                user: 10060_000_000,
-                system: 12060_000_000,
+                user: 10_060_000_000,
+                system: 12_060_000_000,
             },
         };
 
@@ -217,18 +283,18 @@ mod test {
         let measurement1 = CgroupCpuMeasurement {
             precise_time_ns: 60_000_000_000,
             stat: CgroupCpuStat {
-                total_usage: 63800_000_000,
-                user: 10000_000_000,
-                system: 12000_000_000,
+                total_usage: 63_800_000_000,
+                user: 10_000_000_000,
+                system: 12_000_000_000,
             },
         };
 
         let measurement2 = CgroupCpuMeasurement {
             precise_time_ns: 90_000_000_000,
             stat: CgroupCpuStat {
-                total_usage: 10400_000_000,
-                user: 1060_000_000,
-                system: 1260_000_000,
+                total_usage: 10_400_000_000,
+                user: 1_060_000_000,
+                system: 1_260_000_000,
             },
         };
 
@@ -15,6 +15,7 @@ pub fn read_and_parse_v1_sys_stat(
     cpu_period_path: &Path,
     cpu_quota_path: &Path,
     mut cpu_count: Option<f64>,
+    default_cpu_count: Option<f64>,
 ) -> Result<CgroupCpuMeasurement> {
     let time = precise_time_ns();
 
@@ -22,8 +23,8 @@ pub fn read_and_parse_v1_sys_stat(
         // If the CPU period and quota files exist, we can use it to calculate the number of CPUs in
         // the cgroup.
         if cpu_period_path.exists() && cpu_quota_path.exists() {
-            let cpu_period = parse_u64(file_to_string(&cpu_period_path)?.trim())? as f64;
-            let cpu_quota_raw = file_to_string(&cpu_quota_path)?.trim().to_string();
+            let cpu_period = parse_u64(file_to_string(cpu_period_path)?.trim())? as f64;
+            let cpu_quota_raw = file_to_string(cpu_quota_path)?.trim().to_string();
             // The value `-1` means no quota is set and we can't calculate the number of CPUs present.
             if cpu_quota_raw != "-1" {
                 let cpu_quota = parse_u64(&cpu_quota_raw)? as f64;
@@ -31,6 +32,8 @@ pub fn read_and_parse_v1_sys_stat(
             }
         }
     };
+    // Without a quota the cgroup can use all CPUs of the system.
+    let cpu_count = cpu_count.or(default_cpu_count);
 
     let reader = file_to_buf_reader(&path.join("cpuacct.stat"))?;
     let total_usage = read_file_value_as_u64(&path.join("cpuacct.usage"))?;
@@ -45,7 +48,7 @@ pub fn read_and_parse_v1_sys_stat(
     for line in reader.lines() {
         let line = line.map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
         let segments: Vec<&str> = line.split_whitespace().collect();
-        let value = parse_u64(&segments[1])?;
+        let value = parse_u64(segments[1])?;
         fields_encountered += match segments[0] {
             "user" => {
                 cpu.user = value * 10_000_000;
@@ -85,9 +88,10 @@ mod test {
     #[test]
     fn test_read_v1_sys_measurement_no_quota() {
         let measurement = read_and_parse_v1_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
+            None,
             None,
         )
         .unwrap();
@@ -100,9 +104,10 @@ mod test {
     #[test]
     fn test_read_v1_sys_measurement_one_cpu() {
         let measurement = read_and_parse_v1_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.one_cpu"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.one_cpu"),
+            None,
             None,
         )
         .unwrap();
@@ -115,9 +120,10 @@ mod test {
     #[test]
     fn test_read_v1_sys_measurement_two_cpu() {
         let measurement = read_and_parse_v1_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.two_cpu"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.two_cpu"),
+            None,
             None,
         )
         .unwrap();
@@ -130,9 +136,10 @@ mod test {
     #[test]
     fn test_read_v1_sys_measurement_half_cpu() {
         let measurement = read_and_parse_v1_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.half_cpu"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.half_cpu"),
+            None,
             None,
         )
         .unwrap();
@@ -145,9 +152,10 @@ mod test {
     #[test]
     fn test_read_v1_sys_measurement_minus_one() {
         let measurement = read_and_parse_v1_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.minus_one"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.minus_one"),
+            None,
             None,
         )
         .unwrap();
@@ -161,10 +169,11 @@ mod test {
     #[test]
     fn test_read_v1_sys_measurement_one_cpu_count() {
         let measurement = read_and_parse_v1_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.minus_one"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.minus_one"),
             Some(1.0),
+            None,
         )
         .unwrap();
         let cpu = measurement.stat;
@@ -176,10 +185,11 @@ mod test {
     #[test]
     fn test_read_v1_sys_measurement_half_cpu_count() {
         let measurement = read_and_parse_v1_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.minus_one"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.minus_one"),
             Some(0.5),
+            None,
         )
         .unwrap();
         let cpu = measurement.stat;
@@ -191,10 +201,11 @@ mod test {
     #[test]
     fn test_read_v1_sys_measurement_two_cpu_count() {
         let measurement = read_and_parse_v1_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.minus_one"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.minus_one"),
             Some(2.0),
+            None,
         )
         .unwrap();
         let cpu = measurement.stat;
@@ -203,12 +214,47 @@ mod test {
         assert_eq!(cpu.system, 490000000);
     }
 
+    #[test]
+    fn test_read_v1_sys_measurement_default_cpu_count_without_quota() {
+        let measurement = read_and_parse_v1_sys_stat(
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.minus_one"),
+            None,
+            Some(2.0),
+        )
+        .unwrap();
+        let cpu = measurement.stat;
+        // Divides by the default number of CPUs
+        assert_eq!(cpu.total_usage, 76328606511);
+        assert_eq!(cpu.user, 74670000000);
+        assert_eq!(cpu.system, 490000000);
+    }
+
+    #[test]
+    fn test_read_v1_sys_measurement_default_cpu_count_with_quota() {
+        let measurement = read_and_parse_v1_sys_stat(
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.half_cpu"),
+            None,
+            Some(2.0),
+        )
+        .unwrap();
+        let cpu = measurement.stat;
+        // The quota takes precedence over the default number of CPUs
+        assert_eq!(cpu.total_usage, 305314426042);
+        assert_eq!(cpu.user, 298680000000);
+        assert_eq!(cpu.system, 1960000000);
+    }
+
     #[test]
     fn test_read_v1_sys_wrong_path() {
         match read_and_parse_v1_sys_stat(
-            &Path::new("bananas"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
+            Path::new("bananas"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
+            None,
             None,
         ) {
             Err(ProbeError::IO(_, _)) => (),
@@ -219,9 +265,10 @@ mod test {
     #[test]
     fn test_read_and_parse_v1_sys_stat_incomplete() {
         match read_and_parse_v1_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_incomplete/"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_incomplete/"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
+            None,
             None,
         ) {
             Err(ProbeError::UnexpectedContent(_)) => (),
@@ -232,9 +279,10 @@ mod test {
     #[test]
     fn test_read_and_parse_v1_sys_stat_garbage() {
         match read_and_parse_v1_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_garbage/"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_garbage/"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
+            None,
             None,
         ) {
             Err(ProbeError::UnexpectedContent(_)) => (),
@@ -245,17 +293,19 @@ mod test {
     #[test]
     fn test_in_percentages_integration_v1() {
         let mut measurement1 = read_and_parse_v1_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
+            None,
             None,
         )
         .unwrap();
         measurement1.precise_time_ns = 375953965125920;
         let mut measurement2 = read_and_parse_v1_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_2/"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_2/"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/does_not_exist"),
+            None,
             None,
         )
         .unwrap();
@@ -279,17 +329,19 @@ mod test {
     #[test]
     fn test_in_percentages_integration_two_cpu() {
         let mut measurement1 = read_and_parse_v1_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.two_cpu"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.two_cpu"),
+            None,
             None,
         )
         .unwrap();
         measurement1.precise_time_ns = 375953965125920;
         let mut measurement2 = read_and_parse_v1_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_2/"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.two_cpu"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_2/"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.two_cpu"),
+            None,
             None,
         )
         .unwrap();
@@ -313,17 +365,19 @@ mod test {
     #[test]
     fn test_in_percentages_integration_half_cpu() {
         let mut measurement1 = read_and_parse_v1_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.half_cpu"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_1/"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.half_cpu"),
+            None,
             None,
         )
         .unwrap();
         measurement1.precise_time_ns = 375953965125920;
         let mut measurement2 = read_and_parse_v1_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_2/"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.half_cpu"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpuacct_2/"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_period_us"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v1/cpu_quota/cpu.cfs_quota_us.half_cpu"),
+            None,
             None,
         )
         .unwrap();
@@ -11,28 +11,29 @@ pub fn read_and_parse_v2_sys_stat(
     path: &Path,
     cpu_max_path: &Path,
     mut cpu_count: Option<f64>,
+    default_cpu_count: Option<f64>,
 ) -> Result<CgroupCpuMeasurement> {
     // If the cpu.max file exists, we can use it to calculate the number of CPUs
     // in the cgroup. It's also required that the first value is not set to "max",
     // otherwise we can't calculate the number of CPUs.
-    if cpu_count.is_none() {
-        if cpu_max_path.exists() {
-            let reader = file_to_buf_reader(&cpu_max_path)?;
-            let mut lines = reader.lines();
-            if let Some(Ok(line)) = lines.next() {
-                let segments: Vec<&str> = line.split_whitespace().collect();
-                let max = segments[0];
+    if cpu_count.is_none() && cpu_max_path.exists() {
+        let reader = file_to_buf_reader(cpu_max_path)?;
+        let mut lines = reader.lines();
+        if let Some(Ok(line)) = lines.next() {
+            let segments: Vec<&str> = line.split_whitespace().collect();
+            let max = segments[0];
 
-                if max != "max" {
-                    let period = parse_u64(&segments[1])? as f64;
-                    cpu_count = Some(parse_u64(&max)? as f64 / period);
-                }
+            if max != "max" {
+                let period = parse_u64(segments[1])? as f64;
+                cpu_count = Some(parse_u64(max)? as f64 / period);
             }
         }
     }
+    // Without a maximum the cgroup can use all CPUs of the system.
+    let cpu_count = cpu_count.or(default_cpu_count);
 
     let time = precise_time_ns();
-    let reader = file_to_buf_reader(&path)?;
+    let reader = file_to_buf_reader(path)?;
 
     let mut cpu = CgroupCpuStat {
         total_usage: 0,
@@ -44,7 +45,7 @@ pub fn read_and_parse_v2_sys_stat(
     for line in reader.lines() {
         let line = line.map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
         let segments: Vec<&str> = line.split_whitespace().collect();
-        let value = parse_u64(&segments[1])?;
+        let value = parse_u64(segments[1])?;
         fields_encountered += match segments[0] {
             "usage_usec" => {
                 cpu.total_usage = value * 1_000;
@@ -88,8 +89,9 @@ mod test {
     #[test]
     fn test_read_v2_sys_measurement_default_cpu_max() {
         let measurement = read_and_parse_v2_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_1"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_default"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_1"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_default"),
+            None,
             None,
         )
         .unwrap();
@@ -102,8 +104,9 @@ mod test {
     #[test]
     fn test_read_v2_sys_measurement_2_cpus() {
         let measurement = read_and_parse_v2_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_1"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_2_cpus"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_1"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_2_cpus"),
+            None,
             None,
         )
         .unwrap();
@@ -116,8 +119,9 @@ mod test {
     #[test]
     fn test_read_v2_sys_measurement_half_usage() {
         let measurement = read_and_parse_v2_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_1"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_half"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_1"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_half"),
+            None,
             None,
         )
         .unwrap();
@@ -130,9 +134,10 @@ mod test {
     #[test]
     fn test_read_v2_sys_one_cpu_count() {
         let measurement = read_and_parse_v2_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_1"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_garbage"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_1"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_garbage"),
             Some(1.0),
+            None,
         )
         .unwrap();
         let cpu = measurement.stat;
@@ -144,9 +149,10 @@ mod test {
     #[test]
     fn test_read_v2_sys_measurement_two_cpu_count() {
         let measurement = read_and_parse_v2_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_1"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_garbage"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_1"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_garbage"),
             Some(2.0),
+            None,
         )
         .unwrap();
         let cpu = measurement.stat;
@@ -158,9 +164,10 @@ mod test {
     #[test]
     fn test_read_v2_sys_measurement_half_cpu_count() {
         let measurement = read_and_parse_v2_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_1"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_garbage"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_1"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_garbage"),
             Some(0.5),
+            None,
         )
         .unwrap();
         let cpu = measurement.stat;
@@ -169,9 +176,41 @@ mod test {
         assert_eq!(cpu.system, 235340000);
     }
 
+    #[test]
+    fn test_read_v2_sys_measurement_default_cpu_count_without_max() {
+        let measurement = read_and_parse_v2_sys_stat(
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_1"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_default"),
+            None,
+            Some(2.0),
+        )
+        .unwrap();
+        let cpu = measurement.stat;
+        // Divides by the default number of CPUs
+        assert_eq!(cpu.total_usage, 85731000);
+        assert_eq!(cpu.user, 26896000);
+        assert_eq!(cpu.system, 58835000);
+    }
+
+    #[test]
+    fn test_read_v2_sys_measurement_default_cpu_count_with_max() {
+        let measurement = read_and_parse_v2_sys_stat(
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_1"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_half"),
+            None,
+            Some(2.0),
+        )
+        .unwrap();
+        let cpu = measurement.stat;
+        // The maximum takes precedence over the default number of CPUs
+        assert_eq!(cpu.total_usage, 342924000);
+        assert_eq!(cpu.user, 107584000);
+        assert_eq!(cpu.system, 235340000);
+    }
+
     #[test]
     fn test_read_v2_sys_wrong_path() {
-        match read_and_parse_v2_sys_stat(&Path::new("bananas"), &Path::new("potato"), None) {
+        match read_and_parse_v2_sys_stat(Path::new("bananas"), Path::new("potato"), None, None) {
             Err(ProbeError::IO(_, _)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -180,8 +219,9 @@ mod test {
     #[test]
     fn test_read_and_parse_v2_sys_stat_incomplete() {
         match read_and_parse_v2_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_incomplete"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_default"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_incomplete"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_default"),
+            None,
             None,
         ) {
             Err(ProbeError::UnexpectedContent(_)) => (),
@@ -193,7 +233,7 @@ mod test {
     fn test_read_and_parse_v2_sys_stat_garbage() {
         let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_garbage");
         let max_file_path = Path::new("fixtures/linux/fs/cgroup_v2/cpu.max");
-        match read_and_parse_v2_sys_stat(&path, &max_file_path, None) {
+        match read_and_parse_v2_sys_stat(path, max_file_path, None, None) {
             Err(ProbeError::UnexpectedContent(_)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -203,7 +243,7 @@ mod test {
     fn test_read_and_parse_v2_sys_max_garbage() {
         let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_1");
         let max_file_path = Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_garbage");
-        match read_and_parse_v2_sys_stat(&path, &max_file_path, None) {
+        match read_and_parse_v2_sys_stat(path, max_file_path, None, None) {
             Err(ProbeError::UnexpectedContent(_)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -212,15 +252,17 @@ mod test {
     #[test]
     fn test_in_percentages_integration_v2_two_cpu() {
         let mut measurement1 = read_and_parse_v2_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_1"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_2_cpus"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_1"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_2_cpus"),
+            None,
             None,
         )
         .unwrap();
         measurement1.precise_time_ns = 375953965125920;
         let mut measurement2 = read_and_parse_v2_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_2"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_2_cpus"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_2"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_2_cpus"),
+            None,
             None,
         )
         .unwrap();
@@ -245,15 +287,17 @@ mod test {
     #[test]
     fn test_in_percentages_integration_v2_half_cpu() {
         let mut measurement1 = read_and_parse_v2_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_1"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_half"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_1"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_half"),
+            None,
             None,
         )
         .unwrap();
         measurement1.precise_time_ns = 375953965125920;
         let mut measurement2 = read_and_parse_v2_sys_stat(
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_2"),
-            &Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_half"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.stat_2"),
+            Path::new("fixtures/linux/sys/fs/cgroup_v2/cpu.max_half"),
+            None,
             None,
         )
         .unwrap();
@@ -0,0 +1,328 @@
+use crate::{calculate_time_difference, per_minute_value, FieldKind, ProbeError, Result};
+use std::collections::HashMap;
+
+pub type CpuIdleStates = HashMap<String, Vec<IdleState>>;
+
+/// Measurement of the idle state residency of all CPUs at a certain time
+#[derive(Debug, PartialEq)]
+pub struct CpuIdleMeasurement {
+    pub precise_time_ns: u64,
+    pub cpus: CpuIdleStates,
+}
+
+impl CpuIdleMeasurement {
+    /// Calculate the idle state residency per minute based on this measurement and a measurement
+    /// in the future. It is advisable to make the next measurement roughly a minute from this one
+    /// for the most reliable result.
+    pub fn calculate_per_minute(
+        &self,
+        next_measurement: &CpuIdleMeasurement,
+    ) -> Result<CpuIdlePerMinute> {
+        let time_difference =
+            calculate_time_difference(self.precise_time_ns, next_measurement.precise_time_ns)?;
+
+        let mut cpus = CpuIdleStates::new();
+
+        for (name, states) in self.cpus.iter() {
+            let next_states = match next_measurement.cpus.get(name) {
+                Some(states) => states,
+                None => {
+                    return Err(ProbeError::UnexpectedContent(format!(
+                        "{} is not present in the next measurement",
+                        name
+                    )))
+                }
+            };
+
+            if states.len() != next_states.len() {
+                return Err(ProbeError::UnexpectedContent(format!(
+                    "Number of idle states changed for {}",
+                    name
+                )));
+            }
+
+            let mut per_minute = Vec::with_capacity(states.len());
+            for (state, next_state) in states.iter().zip(next_states.iter()) {
+                if state.name != next_state.name {
+                    return Err(ProbeError::UnexpectedContent(format!(
+                        "Idle state {} changed to {} for {}",
+                        state.name, next_state.name, name
+                    )));
+                }
+
+                per_minute.push(IdleState {
+                    name: next_state.name.clone(),
+                    latency_us: next_state.latency_us,
+                    time_us: per_minute_value(
+                        "time_us",
+                        FieldKind::Counter,
+                        next_state.time_us,
+                        state.time_us,
+                        time_difference,
+                    )?,
+                    usage: per_minute_value(
+                        "usage",
+                        FieldKind::Counter,
+                        next_state.usage,
+                        state.usage,
+                        time_difference,
+                    )?,
+                });
+            }
+
+            cpus.insert(name.to_owned(), per_minute);
+        }
+
+        Ok(CpuIdlePerMinute { cpus })
+    }
+}
+
+/// A C-state of a CPU, such as `C1` or `C6`
+#[derive(Debug, PartialEq)]
+pub struct IdleState {
+    pub name: String,
+    /// Exit latency of the state in microseconds
+    pub latency_us: u64,
+    /// Time spent in the state in microseconds
+    pub time_us: u64,
+    /// Number of times the state was entered
+    pub usage: u64,
+}
+
+impl IdleState {
+    /// Percentage of the minute spent in this state, only meaningful for per minute values
+    pub fn residency_percentage(&self) -> f32 {
+        // 60_000_000 being a minute expressed in microseconds.
+        (self.time_us as f64 / 60_000_000.0 * 100.0) as f32
+    }
+}
+
+/// Idle state residency of all CPUs for a minute
+#[derive(Debug, PartialEq)]
+pub struct CpuIdlePerMinute {
+    pub cpus: CpuIdleStates,
+}
+
+/// Read the current idle state residency of all CPUs. CPUs without a cpuidle driver are
+/// not included.
+#[cfg(target_os = "linux")]
+pub fn read() -> Result<CpuIdleMeasurement> {
+    os::read()
+}
+
+#[cfg(target_os = "linux")]
+mod os {
+    use std::path::Path;
+
+    use super::{CpuIdleMeasurement, CpuIdleStates, IdleState};
+    use crate::{
+        dir_exists, file_to_string, numbered_dir_entries, precise_time_ns, read_file_value_as_u64,
+        Result,
+    };
+
+    #[inline]
+    pub fn read() -> Result<CpuIdleMeasurement> {
+        read_and_parse_cpuidle(Path::new("/sys/devices/system/cpu"))
+    }
+
+    pub fn read_and_parse_cpuidle(path: &Path) -> Result<CpuIdleMeasurement> {
+        let time = precise_time_ns();
+
+        let mut cpus = CpuIdleStates::new();
+        for (number, cpu_path) in numbered_dir_entries(path, "cpu")? {
+            let cpuidle_path = cpu_path.join("cpuidle");
+            if !dir_exists(&cpuidle_path) {
+                continue;
+            }
+
+            let mut states = Vec::new();
+            for (_, state_path) in numbered_dir_entries(&cpuidle_path, "state")? {
+                states.push(IdleState {
+                    name: file_to_string(&state_path.join("name"))?.trim().to_owned(),
+                    latency_us: read_file_value_as_u64(&state_path.join("latency"))?,
+                    time_us: read_file_value_as_u64(&state_path.join("time"))?,
+                    usage: read_file_value_as_u64(&state_path.join("usage"))?,
+                });
+            }
+
+            cpus.insert(format!("cpu{}", number), states);
+        }
+
+        Ok(CpuIdleMeasurement {
+            precise_time_ns: time,
+            cpus,
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::os::read_and_parse_cpuidle;
+    use super::{CpuIdleMeasurement, CpuIdleStates, IdleState};
+    use crate::ProbeError;
+    use std::path::Path;
+
+    #[test]
+    fn test_read() {
+        assert!(super::read().is_ok());
+    }
+
+    #[test]
+    fn test_read_and_parse_cpuidle() {
+        let measurement =
+            read_and_parse_cpuidle(Path::new("fixtures/linux/sys/devices/system/cpu")).unwrap();
+
+        // CPUs without a cpuidle directory are skipped
+        assert_eq!(2, measurement.cpus.len());
+
+        let cpu0 = measurement.cpus.get("cpu0").unwrap();
+        assert_eq!(
+            &vec![
+                IdleState {
+                    name: "POLL".to_owned(),
+                    latency_us: 0,
+                    time_us: 1200,
+                    usage: 300,
+                },
+                IdleState {
+                    name: "C1".to_owned(),
+                    latency_us: 2,
+                    time_us: 5000000,
+                    usage: 12000,
+                },
+                IdleState {
+                    name: "C6".to_owned(),
+                    latency_us: 133,
+                    time_us: 40000000,
+                    usage: 8000,
+                },
+            ],
+            cpu0
+        );
+
+        let cpu1 = measurement.cpus.get("cpu1").unwrap();
+        assert_eq!(3, cpu1.len());
+        assert_eq!(35000000, cpu1[2].time_us);
+    }
+
+    #[test]
+    fn test_read_and_parse_cpuidle_wrong_path() {
+        match read_and_parse_cpuidle(Path::new("bananas")) {
+            Err(ProbeError::IO(_, _)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_cpuidle_garbage() {
+        match read_and_parse_cpuidle(Path::new("fixtures/linux/sys/devices/system/cpu_garbage")) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_full_minute() {
+        let measurement1 = helpers::measurement(60_000_000_000, "C6", 10_000_000, 100);
+        let measurement2 = helpers::measurement(120_000_000_000, "C6", 40_000_000, 400);
+
+        let per_minute = measurement1.calculate_per_minute(&measurement2).unwrap();
+        let state = &per_minute.cpus.get("cpu0").unwrap()[0];
+        assert_eq!(
+            &IdleState {
+                name: "C6".to_owned(),
+                latency_us: 133,
+                time_us: 30_000_000,
+                usage: 300,
+            },
+            state
+        );
+        assert_eq!(50.0, state.residency_percentage());
+    }
+
+    #[test]
+    fn test_calculate_per_minute_partial_minute() {
+        let measurement1 = helpers::measurement(60_000_000_000, "C6", 10_000_000, 100);
+        let measurement2 = helpers::measurement(90_000_000_000, "C6", 25_000_000, 400);
+
+        let per_minute = measurement1.calculate_per_minute(&measurement2).unwrap();
+        let state = &per_minute.cpus.get("cpu0").unwrap()[0];
+        assert_eq!(30_000_000, state.time_us);
+        assert_eq!(600, state.usage);
+        assert_eq!(50.0, state.residency_percentage());
+    }
+
+    #[test]
+    fn test_calculate_per_minute_wrong_times() {
+        let measurement1 = helpers::measurement(90_000_000_000, "C6", 10_000_000, 100);
+        let measurement2 = helpers::measurement(60_000_000_000, "C6", 40_000_000, 400);
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::InvalidInput(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_values_lower() {
+        let measurement1 = helpers::measurement(60_000_000_000, "C6", 10_000_000, 100);
+        let measurement2 = helpers::measurement(120_000_000_000, "C6", 5_000_000, 400);
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_different_states() {
+        let measurement1 = helpers::measurement(60_000_000_000, "C6", 10_000_000, 100);
+        let measurement2 = helpers::measurement(120_000_000_000, "C3", 40_000_000, 400);
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_different_cpus() {
+        let measurement1 = helpers::measurement(60_000_000_000, "C6", 10_000_000, 100);
+        let measurement2 = CpuIdleMeasurement {
+            precise_time_ns: 120_000_000_000,
+            cpus: CpuIdleStates::new(),
+        };
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    mod helpers {
+        use super::{CpuIdleMeasurement, CpuIdleStates, IdleState};
+
+        pub fn measurement(
+            precise_time_ns: u64,
+            name: &str,
+            time_us: u64,
+            usage: u64,
+        ) -> CpuIdleMeasurement {
+            let mut cpus = CpuIdleStates::new();
+            cpus.insert(
+                "cpu0".to_owned(),
+                vec![IdleState {
+                    name: name.to_owned(),
+                    latency_us: 133,
+                    time_us,
+                    usage,
+                }],
+            );
+            CpuIdleMeasurement {
+                precise_time_ns,
+                cpus,
+            }
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use crate::Result;
+
+/// Inventory of the CPUs present in the system
+#[derive(Debug, PartialEq)]
+pub struct CpuInfo {
+    pub model_name: Option<String>,
+    pub vendor: Option<String>,
+    pub flags: Vec<String>,
+    pub sockets: u32,
+    pub cores: u32,
+    pub threads: u32,
+    pub online: Vec<u32>,
+}
+
+/// Read the CPU model, topology and online CPUs of the system.
+#[cfg(target_os = "linux")]
+pub fn read() -> Result<CpuInfo> {
+    os::read()
+}
+
+/// Read the list of online CPUs of the system.
+#[cfg(target_os = "linux")]
+pub fn read_online() -> Result<Vec<u32>> {
+    os::read_online()
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) mod os {
+    use std::collections::{BTreeMap, HashSet};
+    use std::io::BufRead;
+    use std::path::Path;
+
+    use super::CpuInfo;
+    use crate::{
+        file_to_buf_reader, file_to_string, parse_u64, path_to_string, ProbeError, Result,
+    };
+
+    #[derive(Debug, Default)]
+    struct Processor {
+        physical_id: Option<String>,
+        core_id: Option<String>,
+    }
+
+    #[inline]
+    pub fn read() -> Result<CpuInfo> {
+        read_and_parse_cpu_info(
+            Path::new("/proc/cpuinfo"),
+            Path::new("/sys/devices/system/cpu/online"),
+            Path::new("/sys/devices/system/cpu"),
+        )
+    }
+
+    #[inline]
+    pub fn read_online() -> Result<Vec<u32>> {
+        read_and_parse_online(Path::new("/sys/devices/system/cpu/online"))
+    }
+
+    pub fn read_and_parse_cpu_info(
+        cpuinfo_path: &Path,
+        online_path: &Path,
+        sys_cpu_dir: &Path,
+    ) -> Result<CpuInfo> {
+        let reader = file_to_buf_reader(cpuinfo_path)?;
+
+        let mut model_name = None;
+        let mut vendor = None;
+        let mut flags = Vec::new();
+        let mut processors: BTreeMap<u32, Processor> = BTreeMap::new();
+        let mut current: Option<u32> = None;
+
+        for line_result in reader.lines() {
+            let line = line_result.map_err(|e| ProbeError::IO(e, path_to_string(cpuinfo_path)))?;
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+
+            match key {
+                "processor" => {
+                    let id = parse_u64(value)? as u32;
+                    processors.insert(id, Processor::default());
+                    current = Some(id);
+                }
+                "model name" if model_name.is_none() => model_name = Some(value.to_owned()),
+                "vendor_id" if vendor.is_none() => vendor = Some(value.to_owned()),
+                // ARM systems list their flags as `Features`
+                "flags" | "Features" if flags.is_empty() => {
+                    flags = value.split_whitespace().map(|f| f.to_owned()).collect()
+                }
+                "physical id" => {
+                    if let Some(processor) = current.and_then(|id| processors.get_mut(&id)) {
+                        processor.physical_id = Some(value.to_owned());
+                    }
+                }
+                "core id" => {
+                    if let Some(processor) = current.and_then(|id| processors.get_mut(&id)) {
+                        processor.core_id = Some(value.to_owned());
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        if processors.is_empty() {
+            return Err(ProbeError::UnexpectedContent(
+                "No processors found".to_owned(),
+            ));
+        }
+
+        // Fall back to the processors listed in cpuinfo when the online file is not available
+        let online = if online_path.exists() {
+            read_and_parse_online(online_path)?
+        } else {
+            processors.keys().cloned().collect()
+        };
+
+        let mut sockets = HashSet::new();
+        let mut cores = HashSet::new();
+        for cpu in online.iter() {
+            let topology_dir = sys_cpu_dir.join(format!("cpu{}/topology", cpu));
+            let processor = processors.get(cpu);
+
+            let physical_id = read_topology_value(&topology_dir.join("physical_package_id"))?
+                .or_else(|| processor.and_then(|p| p.physical_id.clone()))
+                .unwrap_or_else(|| "0".to_owned());
+            // Without core information every CPU is counted as a separate core
+            let core_id = read_topology_value(&topology_dir.join("core_id"))?
+                .or_else(|| processor.and_then(|p| p.core_id.clone()))
+                .unwrap_or_else(|| cpu.to_string());
+
+            sockets.insert(physical_id.clone());
+            cores.insert((physical_id, core_id));
+        }
+
+        Ok(CpuInfo {
+            model_name,
+            vendor,
+            flags,
+            sockets: sockets.len() as u32,
+            cores: cores.len() as u32,
+            threads: online.len() as u32,
+            online,
+        })
+    }
+
+    /// Parse a CPU list such as `0-3,5,7-8`, as used by the online CPUs and cgroup cpusets
+    pub fn read_and_parse_online(path: &Path) -> Result<Vec<u32>> {
+        let raw = file_to_string(path)?;
+        let mut out = Vec::new();
+
+        for range in raw.trim().split(',').filter(|r| !r.is_empty()) {
+            match range.split_once('-') {
+                Some((start, end)) => {
+                    let start = parse_u64(start)? as u32;
+                    let end = parse_u64(end)? as u32;
+                    if start > end {
+                        return Err(ProbeError::UnexpectedContent(format!(
+                            "Invalid CPU range '{}'",
+                            range
+                        )));
+                    }
+                    out.extend(start..=end);
+                }
+                None => out.push(parse_u64(range)? as u32),
+            }
+        }
+
+        Ok(out)
+    }
+
+    #[inline]
+    fn read_topology_value(path: &Path) -> Result<Option<String>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(file_to_string(path)?.trim().to_owned()))
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::os::{read_and_parse_cpu_info, read_and_parse_online};
+    use crate::ProbeError;
+    use std::path::Path;
+
+    #[test]
+    fn test_read() {
+        let info = super::read().unwrap();
+        assert!(info.threads > 0);
+        assert!(info.cores > 0);
+        assert!(info.sockets > 0);
+    }
+
+    #[test]
+    fn test_read_online() {
+        assert!(!super::read_online().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_and_parse_cpu_info() {
+        let info = read_and_parse_cpu_info(
+            Path::new("fixtures/linux/cpu/proc_cpuinfo"),
+            Path::new("fixtures/linux/sys/devices/system/cpu/online"),
+            Path::new("fixtures/linux/sys/devices/system/cpu"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some("Intel(R) Xeon(R) Platinum 8175M CPU @ 2.50GHz".to_owned()),
+            info.model_name
+        );
+        assert_eq!(Some("GenuineIntel".to_owned()), info.vendor);
+        assert_eq!(34, info.flags.len());
+        assert!(info.flags.contains(&"avx2".to_owned()));
+        assert_eq!(1, info.sockets);
+        assert_eq!(2, info.cores);
+        assert_eq!(4, info.threads);
+        assert_eq!(vec![0, 1, 2, 3], info.online);
+    }
+
+    #[test]
+    fn test_read_and_parse_cpu_info_without_sysfs() {
+        // Topology is read from cpuinfo instead
+        let info = read_and_parse_cpu_info(
+            Path::new("fixtures/linux/cpu/proc_cpuinfo"),
+            Path::new("fixtures/linux/sys/devices/system/cpu/does_not_exist"),
+            Path::new("fixtures/linux/sys/devices/system/does_not_exist"),
+        )
+        .unwrap();
+
+        assert_eq!(1, info.sockets);
+        assert_eq!(2, info.cores);
+        assert_eq!(4, info.threads);
+        assert_eq!(vec![0, 1, 2, 3], info.online);
+    }
+
+    #[test]
+    fn test_read_and_parse_cpu_info_sparse_online() {
+        let info = read_and_parse_cpu_info(
+            Path::new("fixtures/linux/cpu/proc_cpuinfo"),
+            Path::new("fixtures/linux/sys/devices/system/cpu/online_sparse"),
+            Path::new("fixtures/linux/sys/devices/system/cpu"),
+        )
+        .unwrap();
+
+        assert_eq!(1, info.sockets);
+        assert_eq!(2, info.cores);
+        assert_eq!(3, info.threads);
+        assert_eq!(vec![0, 1, 3], info.online);
+    }
+
+    #[test]
+    fn test_read_and_parse_cpu_info_arm() {
+        let info = read_and_parse_cpu_info(
+            Path::new("fixtures/linux/cpu/proc_cpuinfo_arm"),
+            Path::new("fixtures/linux/sys/devices/system/cpu/does_not_exist"),
+            Path::new("fixtures/linux/sys/devices/system/does_not_exist"),
+        )
+        .unwrap();
+
+        assert_eq!(None, info.model_name);
+        assert_eq!(None, info.vendor);
+        assert_eq!(17, info.flags.len());
+        assert_eq!(1, info.sockets);
+        assert_eq!(2, info.cores);
+        assert_eq!(2, info.threads);
+        assert_eq!(vec![0, 1], info.online);
+    }
+
+    #[test]
+    fn test_read_and_parse_cpu_info_wrong_path() {
+        match read_and_parse_cpu_info(
+            Path::new("bananas"),
+            Path::new("bananas"),
+            Path::new("bananas"),
+        ) {
+            Err(ProbeError::IO(_, _)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_cpu_info_garbage() {
+        match read_and_parse_cpu_info(
+            Path::new("fixtures/linux/cpu/proc_cpuinfo_garbage"),
+            Path::new("fixtures/linux/sys/devices/system/cpu/online"),
+            Path::new("fixtures/linux/sys/devices/system/cpu"),
+        ) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_online() {
+        assert_eq!(
+            vec![0, 1, 2, 3],
+            read_and_parse_online(Path::new("fixtures/linux/sys/devices/system/cpu/online"))
+                .unwrap()
+        );
+        assert_eq!(
+            vec![0, 1, 3],
+            read_and_parse_online(Path::new(
+                "fixtures/linux/sys/devices/system/cpu/online_sparse"
+            ))
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_read_and_parse_online_garbage() {
+        match read_and_parse_online(Path::new(
+            "fixtures/linux/sys/devices/system/cpu/online_garbage",
+        )) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+}
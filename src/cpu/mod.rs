@@ -1,4 +1,7 @@
 pub mod cgroup;
 mod cgroup_v1;
 mod cgroup_v2;
+pub mod idle;
+pub mod info;
 pub mod proc;
+pub mod schedstat;
@@ -1,4 +1,4 @@
-use super::super::{calculate_time_difference, time_adjusted, Result};
+use super::super::{calculate_time_difference, per_minute_value, FieldKind, Result};
 
 /// Measurement of cpu stats at a certain time
 #[derive(Debug, PartialEq)]
@@ -16,68 +16,79 @@ impl CpuMeasurement {
             calculate_time_difference(self.precise_time_ns, next_measurement.precise_time_ns)?;
 
         Ok(CpuStat {
-            total: time_adjusted(
+            total: per_minute_value(
                 "total",
+                FieldKind::Counter,
                 next_measurement.stat.total,
                 self.stat.total,
                 time_difference,
             )?,
-            user: time_adjusted(
+            user: per_minute_value(
                 "user",
+                FieldKind::Counter,
                 next_measurement.stat.user,
                 self.stat.user,
                 time_difference,
             )?,
-            nice: time_adjusted(
+            nice: per_minute_value(
                 "nice",
+                FieldKind::Counter,
                 next_measurement.stat.nice,
                 self.stat.nice,
                 time_difference,
             )?,
-            system: time_adjusted(
+            system: per_minute_value(
                 "system",
+                FieldKind::Counter,
                 next_measurement.stat.system,
                 self.stat.system,
                 time_difference,
             )?,
-            idle: time_adjusted(
+            idle: per_minute_value(
                 "idle",
+                FieldKind::Counter,
                 next_measurement.stat.idle,
                 self.stat.idle,
                 time_difference,
             )?,
-            iowait: time_adjusted(
+            iowait: per_minute_value(
                 "iowait",
+                FieldKind::Counter,
                 next_measurement.stat.iowait,
                 self.stat.iowait,
                 time_difference,
             )?,
-            irq: time_adjusted(
+            irq: per_minute_value(
                 "irq",
+                FieldKind::Counter,
                 next_measurement.stat.irq,
                 self.stat.irq,
                 time_difference,
             )?,
-            softirq: time_adjusted(
+            softirq: per_minute_value(
                 "softirq",
+                FieldKind::Counter,
                 next_measurement.stat.softirq,
                 self.stat.softirq,
                 time_difference,
             )?,
-            steal: time_adjusted(
+            steal: per_minute_value(
                 "steal",
+                FieldKind::Counter,
                 next_measurement.stat.steal,
                 self.stat.steal,
                 time_difference,
             )?,
-            guest: time_adjusted(
+            guest: per_minute_value(
                 "guest",
+                FieldKind::Counter,
                 next_measurement.stat.guest,
                 self.stat.guest,
                 time_difference,
             )?,
-            guestnice: time_adjusted(
+            guestnice: per_minute_value(
                 "guestnice",
+                FieldKind::Counter,
                 next_measurement.stat.guestnice,
                 self.stat.guestnice,
                 time_difference,
@@ -159,7 +170,7 @@ mod os {
 
     #[inline]
     pub fn read() -> Result<CpuMeasurement> {
-        read_and_parse_proc_stat(&Path::new("/proc/stat"))
+        read_and_parse_proc_stat(Path::new("/proc/stat"))
     }
 
     pub fn read_and_parse_proc_stat(path: &Path) -> Result<CpuMeasurement> {
@@ -183,8 +194,8 @@ mod os {
 
         let usertime = parse_u64(stats[0])?;
         let nicetime = parse_u64(stats[1])?;
-        let guest = parse_u64(*stats.get(8).unwrap_or(&"0"))?;
-        let guestnice = parse_u64(*stats.get(9).unwrap_or(&"0"))?;
+        let guest = parse_u64(stats.get(8).unwrap_or(&"0"))?;
+        let guestnice = parse_u64(stats.get(9).unwrap_or(&"0"))?;
 
         let mut cpu = CpuStat {
             total: 0,
@@ -193,9 +204,9 @@ mod os {
             system: parse_u64(stats[2])?,
             idle: parse_u64(stats[3])?,
             iowait: parse_u64(stats[4])?,
-            irq: parse_u64(*stats.get(5).unwrap_or(&"0"))?,
-            softirq: parse_u64(*stats.get(6).unwrap_or(&"0"))?,
-            steal: parse_u64(*stats.get(7).unwrap_or(&"0"))?,
+            irq: parse_u64(stats.get(5).unwrap_or(&"0"))?,
+            softirq: parse_u64(stats.get(6).unwrap_or(&"0"))?,
+            steal: parse_u64(stats.get(7).unwrap_or(&"0"))?,
             guest,
             guestnice,
         };
@@ -227,7 +238,7 @@ mod test {
     #[test]
     fn test_read_proc_measurement() {
         let measurement =
-            read_and_parse_proc_stat(&Path::new("fixtures/linux/cpu/proc_stat")).unwrap();
+            read_and_parse_proc_stat(Path::new("fixtures/linux/cpu/proc_stat")).unwrap();
         let cpu = measurement.stat;
         assert_eq!(cpu.total, 39);
         assert_eq!(cpu.user, 8);
@@ -245,7 +256,7 @@ mod test {
     #[test]
     fn test_read_proc_measurement_from_partial() {
         let measurement =
-            read_and_parse_proc_stat(&Path::new("fixtures/linux/cpu/proc_stat_partial")).unwrap();
+            read_and_parse_proc_stat(Path::new("fixtures/linux/cpu/proc_stat_partial")).unwrap();
         let cpu = measurement.stat;
         assert_eq!(cpu.total, 31);
         assert_eq!(cpu.user, 10);
@@ -262,7 +273,7 @@ mod test {
 
     #[test]
     fn test_proc_wrong_path() {
-        match read_and_parse_proc_stat(&Path::new("bananas")) {
+        match read_and_parse_proc_stat(Path::new("bananas")) {
             Err(ProbeError::IO(_, _)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -270,7 +281,7 @@ mod test {
 
     #[test]
     fn test_read_and_parse_proc_stat_incomplete() {
-        match read_and_parse_proc_stat(&Path::new("fixtures/linux/cpu/proc_stat_incomplete")) {
+        match read_and_parse_proc_stat(Path::new("fixtures/linux/cpu/proc_stat_incomplete")) {
             Err(ProbeError::UnexpectedContent(_)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -279,7 +290,7 @@ mod test {
     #[test]
     fn test_read_and_parse_proc_stat_garbage() {
         let path = Path::new("fixtures/linux/cpu/proc_stat_garbage");
-        match read_and_parse_proc_stat(&path) {
+        match read_and_parse_proc_stat(path) {
             Err(ProbeError::UnexpectedContent(_)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -548,10 +559,10 @@ mod test {
     #[test]
     fn test_in_percentages_integration() {
         let mut measurement1 =
-            read_and_parse_proc_stat(&Path::new("fixtures/linux/cpu/proc_stat_1")).unwrap();
+            read_and_parse_proc_stat(Path::new("fixtures/linux/cpu/proc_stat_1")).unwrap();
         measurement1.precise_time_ns = 60_000_000_000;
         let mut measurement2 =
-            read_and_parse_proc_stat(&Path::new("fixtures/linux/cpu/proc_stat_2")).unwrap();
+            read_and_parse_proc_stat(Path::new("fixtures/linux/cpu/proc_stat_2")).unwrap();
         measurement2.precise_time_ns = 120_000_000_000;
 
         let stat = measurement1.calculate_per_minute(&measurement2).unwrap();
@@ -0,0 +1,493 @@
+use crate::{calculate_time_difference, per_minute_value, FieldKind, ProbeError, Result};
+use std::collections::HashMap;
+
+pub type CpuSchedstats = HashMap<String, Schedstat>;
+
+/// Measurement of the scheduler statistics of all CPUs at a certain time
+#[derive(Debug, PartialEq)]
+pub struct SchedstatMeasurement {
+    pub precise_time_ns: u64,
+    pub cpus: CpuSchedstats,
+}
+
+impl SchedstatMeasurement {
+    /// Calculate the scheduler stats per minute based on this measurement and a measurement in
+    /// the future. It is advisable to make the next measurement roughly a minute from this one
+    /// for the most reliable result.
+    pub fn calculate_per_minute(
+        &self,
+        next_measurement: &SchedstatMeasurement,
+    ) -> Result<SchedstatPerMinute> {
+        let time_difference =
+            calculate_time_difference(self.precise_time_ns, next_measurement.precise_time_ns)?;
+
+        let mut cpus = CpuSchedstats::new();
+
+        for (name, stat) in self.cpus.iter() {
+            let next_stat = match next_measurement.cpus.get(name) {
+                Some(stat) => stat,
+                None => {
+                    return Err(ProbeError::UnexpectedContent(format!(
+                        "{} is not present in the next measurement",
+                        name
+                    )))
+                }
+            };
+
+            cpus.insert(
+                name.to_owned(),
+                next_stat.time_adjusted(stat, time_difference)?,
+            );
+        }
+
+        Ok(SchedstatPerMinute { cpus })
+    }
+}
+
+/// Measurement of the scheduler statistics of a single process at a certain time
+#[derive(Debug, PartialEq)]
+pub struct ProcessSchedstatMeasurement {
+    pub precise_time_ns: u64,
+    pub stat: Schedstat,
+}
+
+impl ProcessSchedstatMeasurement {
+    /// Calculate the scheduler stats per minute based on this measurement and a measurement in
+    /// the future. It is advisable to make the next measurement roughly a minute from this one
+    /// for the most reliable result.
+    pub fn calculate_per_minute(
+        &self,
+        next_measurement: &ProcessSchedstatMeasurement,
+    ) -> Result<Schedstat> {
+        let time_difference =
+            calculate_time_difference(self.precise_time_ns, next_measurement.precise_time_ns)?;
+
+        next_measurement
+            .stat
+            .time_adjusted(&self.stat, time_difference)
+    }
+}
+
+/// Time spent running and waiting on a run queue, in nanoseconds
+#[derive(Debug, PartialEq)]
+pub struct Schedstat {
+    pub run_time_ns: u64,
+    pub run_delay_ns: u64,
+    pub timeslices: u64,
+}
+
+impl Schedstat {
+    /// Average time a task waited on the run queue before it got to run, in nanoseconds.
+    /// Returns `None` if nothing was scheduled.
+    pub fn average_delay_ns(&self) -> Option<u64> {
+        self.run_delay_ns.checked_div(self.timeslices)
+    }
+
+    fn time_adjusted(&self, previous: &Schedstat, time_difference: u64) -> Result<Schedstat> {
+        Ok(Schedstat {
+            run_time_ns: per_minute_value(
+                "run_time_ns",
+                FieldKind::Counter,
+                self.run_time_ns,
+                previous.run_time_ns,
+                time_difference,
+            )?,
+            run_delay_ns: per_minute_value(
+                "run_delay_ns",
+                FieldKind::Counter,
+                self.run_delay_ns,
+                previous.run_delay_ns,
+                time_difference,
+            )?,
+            timeslices: per_minute_value(
+                "timeslices",
+                FieldKind::Counter,
+                self.timeslices,
+                previous.timeslices,
+                time_difference,
+            )?,
+        })
+    }
+}
+
+/// Scheduler stats of all CPUs for a minute
+#[derive(Debug, PartialEq)]
+pub struct SchedstatPerMinute {
+    pub cpus: CpuSchedstats,
+}
+
+impl SchedstatPerMinute {
+    /// Scheduler stats summed over all CPUs
+    pub fn total(&self) -> Schedstat {
+        self.cpus.values().fold(
+            Schedstat {
+                run_time_ns: 0,
+                run_delay_ns: 0,
+                timeslices: 0,
+            },
+            |total, stat| Schedstat {
+                run_time_ns: total.run_time_ns + stat.run_time_ns,
+                run_delay_ns: total.run_delay_ns + stat.run_delay_ns,
+                timeslices: total.timeslices + stat.timeslices,
+            },
+        )
+    }
+}
+
+/// Read the current scheduler stats of all CPUs. Requires a kernel with `CONFIG_SCHEDSTATS`.
+#[cfg(target_os = "linux")]
+pub fn read() -> Result<SchedstatMeasurement> {
+    os::read()
+}
+
+/// Read the current scheduler stats of this process.
+#[cfg(target_os = "linux")]
+pub fn read_current_process() -> Result<ProcessSchedstatMeasurement> {
+    os::read_current_process()
+}
+
+/// Read the current scheduler stats of a process with given pid.
+#[cfg(target_os = "linux")]
+pub fn read_process(pid: libc::pid_t) -> Result<ProcessSchedstatMeasurement> {
+    os::read_process(pid)
+}
+
+#[cfg(target_os = "linux")]
+mod os {
+    use std::io::BufRead;
+    use std::path::Path;
+
+    use super::{CpuSchedstats, ProcessSchedstatMeasurement, Schedstat, SchedstatMeasurement};
+    use crate::{
+        file_to_buf_reader, file_to_string, parse_u64, path_to_string, precise_time_ns, ProbeError,
+        Result,
+    };
+
+    // The cpu lines have been stable since version 15 of the schedstat format
+    const MINIMUM_SCHEDSTAT_VERSION: u64 = 15;
+
+    #[inline]
+    pub fn read() -> Result<SchedstatMeasurement> {
+        read_and_parse_schedstat(Path::new("/proc/schedstat"))
+    }
+
+    #[inline]
+    pub fn read_current_process() -> Result<ProcessSchedstatMeasurement> {
+        read_and_parse_process_schedstat(Path::new("/proc/self/schedstat"))
+    }
+
+    #[inline]
+    pub fn read_process(pid: libc::pid_t) -> Result<ProcessSchedstatMeasurement> {
+        read_and_parse_process_schedstat(Path::new(&format!("/proc/{}/schedstat", pid)))
+    }
+
+    pub fn read_and_parse_schedstat(path: &Path) -> Result<SchedstatMeasurement> {
+        let reader = file_to_buf_reader(path)?;
+        let time = precise_time_ns();
+
+        let mut cpus = CpuSchedstats::new();
+        for line_result in reader.lines() {
+            let line = line_result.map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
+            let segments: Vec<&str> = line.split_whitespace().collect();
+
+            match segments.first() {
+                Some(&"version") => {
+                    let version = parse_u64(segments.get(1).unwrap_or(&""))?;
+                    if version < MINIMUM_SCHEDSTAT_VERSION {
+                        return Err(ProbeError::UnexpectedContent(format!(
+                            "Unsupported schedstat version {}",
+                            version
+                        )));
+                    }
+                }
+                Some(name) if name.starts_with("cpu") => {
+                    // columns: yld_count legacy sched_count sched_goidle ttwu_count ttwu_local
+                    // rq_cpu_time run_delay pcount
+                    if segments.len() < 10 {
+                        return Err(ProbeError::UnexpectedContent(
+                            "Incorrect number of segments".to_owned(),
+                        ));
+                    }
+
+                    cpus.insert(
+                        name.to_string(),
+                        Schedstat {
+                            run_time_ns: parse_u64(segments[7])?,
+                            run_delay_ns: parse_u64(segments[8])?,
+                            timeslices: parse_u64(segments[9])?,
+                        },
+                    );
+                }
+                _ => (),
+            }
+        }
+
+        Ok(SchedstatMeasurement {
+            precise_time_ns: time,
+            cpus,
+        })
+    }
+
+    pub fn read_and_parse_process_schedstat(path: &Path) -> Result<ProcessSchedstatMeasurement> {
+        let raw_data = file_to_string(path)?;
+        let time = precise_time_ns();
+        let segments: Vec<&str> = raw_data.split_whitespace().collect();
+
+        // columns: run_time run_delay timeslices
+        if segments.len() < 3 {
+            return Err(ProbeError::UnexpectedContent(
+                "Incorrect number of segments".to_owned(),
+            ));
+        }
+
+        Ok(ProcessSchedstatMeasurement {
+            precise_time_ns: time,
+            stat: Schedstat {
+                run_time_ns: parse_u64(segments[0])?,
+                run_delay_ns: parse_u64(segments[1])?,
+                timeslices: parse_u64(segments[2])?,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::os::{read_and_parse_process_schedstat, read_and_parse_schedstat};
+    use super::{CpuSchedstats, ProcessSchedstatMeasurement, Schedstat, SchedstatMeasurement};
+    use crate::ProbeError;
+    use std::path::Path;
+
+    #[test]
+    fn test_read() {
+        // Only available on kernels built with CONFIG_SCHEDSTATS
+        if Path::new("/proc/schedstat").exists() {
+            assert!(!super::read().unwrap().cpus.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_read_current_process() {
+        assert!(super::read_current_process().is_ok());
+    }
+
+    #[test]
+    fn test_read_process() {
+        let pid = unsafe { libc::getpid() };
+        assert!(super::read_process(pid).is_ok());
+    }
+
+    #[test]
+    fn test_read_process_invalid_pid() {
+        assert!(super::read_process(0).is_err());
+    }
+
+    #[test]
+    fn test_read_and_parse_schedstat() {
+        let measurement =
+            read_and_parse_schedstat(Path::new("fixtures/linux/cpu/proc_schedstat")).unwrap();
+
+        assert_eq!(2, measurement.cpus.len());
+
+        let cpu0 = measurement.cpus.get("cpu0").unwrap();
+        assert_eq!(1503278845261, cpu0.run_time_ns);
+        assert_eq!(72918442512, cpu0.run_delay_ns);
+        assert_eq!(9208341, cpu0.timeslices);
+
+        let cpu1 = measurement.cpus.get("cpu1").unwrap();
+        assert_eq!(1423099112093, cpu1.run_time_ns);
+        assert_eq!(91230459832, cpu1.run_delay_ns);
+        assert_eq!(8720184, cpu1.timeslices);
+    }
+
+    #[test]
+    fn test_read_and_parse_schedstat_wrong_path() {
+        match read_and_parse_schedstat(Path::new("bananas")) {
+            Err(ProbeError::IO(_, _)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_schedstat_incomplete() {
+        match read_and_parse_schedstat(Path::new("fixtures/linux/cpu/proc_schedstat_incomplete")) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_schedstat_garbage() {
+        match read_and_parse_schedstat(Path::new("fixtures/linux/cpu/proc_schedstat_garbage")) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_schedstat_old_version() {
+        match read_and_parse_schedstat(Path::new("fixtures/linux/cpu/proc_schedstat_version_14")) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_process_schedstat() {
+        let measurement =
+            read_and_parse_process_schedstat(Path::new("fixtures/linux/cpu/proc_pid_schedstat"))
+                .unwrap();
+
+        let expected = Schedstat {
+            run_time_ns: 1230948021,
+            run_delay_ns: 53428032,
+            timeslices: 4512,
+        };
+        assert_eq!(expected, measurement.stat);
+    }
+
+    #[test]
+    fn test_read_and_parse_process_schedstat_incomplete() {
+        match read_and_parse_process_schedstat(Path::new(
+            "fixtures/linux/cpu/proc_pid_schedstat_incomplete",
+        )) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_full_minute() {
+        let measurement1 = helpers::measurement(60_000_000_000, 1_000_000, 50_000, 100);
+        let measurement2 = helpers::measurement(120_000_000_000, 7_000_000, 650_000, 400);
+
+        let per_minute = measurement1.calculate_per_minute(&measurement2).unwrap();
+        let expected = Schedstat {
+            run_time_ns: 6_000_000,
+            run_delay_ns: 600_000,
+            timeslices: 300,
+        };
+        assert_eq!(&expected, per_minute.cpus.get("cpu0").unwrap());
+        assert_eq!(Some(2_000), expected.average_delay_ns());
+        assert_eq!(expected, per_minute.total());
+    }
+
+    #[test]
+    fn test_calculate_per_minute_partial_minute() {
+        let measurement1 = helpers::measurement(60_000_000_000, 1_000_000, 50_000, 100);
+        let measurement2 = helpers::measurement(90_000_000_000, 7_000_000, 650_000, 400);
+
+        let per_minute = measurement1.calculate_per_minute(&measurement2).unwrap();
+        let expected = Schedstat {
+            run_time_ns: 12_000_000,
+            run_delay_ns: 1_200_000,
+            timeslices: 600,
+        };
+        assert_eq!(&expected, per_minute.cpus.get("cpu0").unwrap());
+        // The average delay does not depend on the length of the interval
+        assert_eq!(Some(2_000), expected.average_delay_ns());
+    }
+
+    #[test]
+    fn test_calculate_per_minute_wrong_times() {
+        let measurement1 = helpers::measurement(90_000_000_000, 1_000_000, 50_000, 100);
+        let measurement2 = helpers::measurement(60_000_000_000, 7_000_000, 650_000, 400);
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::InvalidInput(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_values_lower() {
+        let measurement1 = helpers::measurement(60_000_000_000, 1_000_000, 50_000, 100);
+        let measurement2 = helpers::measurement(120_000_000_000, 7_000_000, 40_000, 400);
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_different_cpus() {
+        let measurement1 = helpers::measurement(60_000_000_000, 1_000_000, 50_000, 100);
+        let measurement2 = SchedstatMeasurement {
+            precise_time_ns: 120_000_000_000,
+            cpus: CpuSchedstats::new(),
+        };
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_process_calculate_per_minute() {
+        let measurement1 = ProcessSchedstatMeasurement {
+            precise_time_ns: 60_000_000_000,
+            stat: Schedstat {
+                run_time_ns: 1_000_000,
+                run_delay_ns: 50_000,
+                timeslices: 100,
+            },
+        };
+        let measurement2 = ProcessSchedstatMeasurement {
+            precise_time_ns: 90_000_000_000,
+            stat: Schedstat {
+                run_time_ns: 4_000_000,
+                run_delay_ns: 80_000,
+                timeslices: 130,
+            },
+        };
+
+        let expected = Schedstat {
+            run_time_ns: 6_000_000,
+            run_delay_ns: 60_000,
+            timeslices: 60,
+        };
+        assert_eq!(
+            expected,
+            measurement1.calculate_per_minute(&measurement2).unwrap()
+        );
+        assert_eq!(Some(1_000), expected.average_delay_ns());
+    }
+
+    #[test]
+    fn test_average_delay_ns_nothing_scheduled() {
+        let stat = Schedstat {
+            run_time_ns: 0,
+            run_delay_ns: 0,
+            timeslices: 0,
+        };
+        assert_eq!(None, stat.average_delay_ns());
+    }
+
+    mod helpers {
+        use super::{CpuSchedstats, Schedstat, SchedstatMeasurement};
+
+        pub fn measurement(
+            precise_time_ns: u64,
+            run_time_ns: u64,
+            run_delay_ns: u64,
+            timeslices: u64,
+        ) -> SchedstatMeasurement {
+            let mut cpus = CpuSchedstats::new();
+            cpus.insert(
+                "cpu0".to_owned(),
+                Schedstat {
+                    run_time_ns,
+                    run_delay_ns,
+                    timeslices,
+                },
+            );
+            SchedstatMeasurement {
+                precise_time_ns,
+                cpus,
+            }
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use super::{calculate_time_difference, time_adjusted, Result};
+use super::{
+    calculate_time_difference, counter_difference, per_minute_value, per_minute_value_option,
+    FieldKind, Result,
+};
 use crate::error::ProbeError;
 use std::collections::HashMap;
 use std::path::Path;
@@ -12,9 +15,18 @@ pub struct DiskStatsMeasurement {
 }
 
 impl DiskStatsMeasurement {
+    /// Names of the devices in this measurement by their major and minor number
+    pub fn device_names(&self) -> HashMap<(u64, u64), String> {
+        self.stats
+            .iter()
+            .map(|(name, stat)| ((stat.major, stat.minor), name.to_owned()))
+            .collect()
+    }
+
     /// Calculate the disk stats per minute based on this measurement and a measurement in the
     /// future. It is advisable to make the next measurement roughly a minute from this one for the
-    /// most reliable result.
+    /// most reliable result. The `ios_currently_in_progress` gauge is reported as the value of
+    /// the next measurement.
     pub fn calculate_per_minute(
         &self,
         next_measurement: &DiskStatsMeasurement,
@@ -38,82 +50,237 @@ impl DiskStatsMeasurement {
             stats.insert(
                 name.to_owned(),
                 DiskStat {
-                    reads_completed_successfully: time_adjusted(
+                    major: next_stat.major,
+                    minor: next_stat.minor,
+                    reads_completed_successfully: per_minute_value(
                         "reads_completed_successfully",
+                        FieldKind::Counter,
                         next_stat.reads_completed_successfully,
                         stat.reads_completed_successfully,
                         time_difference,
                     )?,
-                    reads_merged: time_adjusted(
+                    reads_merged: per_minute_value(
                         "reads_merged",
+                        FieldKind::Counter,
                         next_stat.reads_merged,
                         stat.reads_merged,
                         time_difference,
                     )?,
-                    sectors_read: time_adjusted(
+                    sectors_read: per_minute_value(
                         "sectors_read",
+                        FieldKind::Counter,
                         next_stat.sectors_read,
                         stat.sectors_read,
                         time_difference,
                     )?,
-                    time_spent_reading_ms: time_adjusted(
+                    time_spent_reading_ms: per_minute_value(
                         "time_spent_reading_ms",
+                        FieldKind::Counter,
                         next_stat.time_spent_reading_ms,
                         stat.time_spent_reading_ms,
                         time_difference,
                     )?,
-                    writes_completed: time_adjusted(
+                    writes_completed: per_minute_value(
                         "writes_completed",
+                        FieldKind::Counter,
                         next_stat.writes_completed,
                         stat.writes_completed,
                         time_difference,
                     )?,
-                    writes_merged: time_adjusted(
+                    writes_merged: per_minute_value(
                         "writes_merged",
+                        FieldKind::Counter,
                         next_stat.writes_merged,
                         stat.writes_merged,
                         time_difference,
                     )?,
-                    sectors_written: time_adjusted(
+                    sectors_written: per_minute_value(
                         "sectors_written",
+                        FieldKind::Counter,
                         next_stat.sectors_written,
                         stat.sectors_written,
                         time_difference,
                     )?,
-                    time_spent_writing_ms: time_adjusted(
+                    time_spent_writing_ms: per_minute_value(
                         "time_spent_writing_ms",
+                        FieldKind::Counter,
                         next_stat.time_spent_writing_ms,
                         stat.time_spent_writing_ms,
                         time_difference,
                     )?,
-                    ios_currently_in_progress: time_adjusted(
+                    ios_currently_in_progress: per_minute_value(
                         "ios_currently_in_progress",
+                        FieldKind::Gauge,
                         next_stat.ios_currently_in_progress,
                         stat.ios_currently_in_progress,
                         time_difference,
                     )?,
-                    time_spent_doing_ios_ms: time_adjusted(
+                    time_spent_doing_ios_ms: per_minute_value(
                         "time_spent_doing_ios_ms",
+                        FieldKind::Counter,
                         next_stat.time_spent_doing_ios_ms,
                         stat.time_spent_doing_ios_ms,
                         time_difference,
                     )?,
-                    weighted_time_spent_doing_ios_ms: time_adjusted(
+                    weighted_time_spent_doing_ios_ms: per_minute_value(
                         "weighted_time_spent_doing_ios_ms",
+                        FieldKind::Counter,
                         next_stat.weighted_time_spent_doing_ios_ms,
                         stat.weighted_time_spent_doing_ios_ms,
                         time_difference,
                     )?,
+                    discards_completed: per_minute_value_option(
+                        "discards_completed",
+                        FieldKind::Counter,
+                        next_stat.discards_completed,
+                        stat.discards_completed,
+                        time_difference,
+                    )?,
+                    discards_merged: per_minute_value_option(
+                        "discards_merged",
+                        FieldKind::Counter,
+                        next_stat.discards_merged,
+                        stat.discards_merged,
+                        time_difference,
+                    )?,
+                    sectors_discarded: per_minute_value_option(
+                        "sectors_discarded",
+                        FieldKind::Counter,
+                        next_stat.sectors_discarded,
+                        stat.sectors_discarded,
+                        time_difference,
+                    )?,
+                    time_spent_discarding_ms: per_minute_value_option(
+                        "time_spent_discarding_ms",
+                        FieldKind::Counter,
+                        next_stat.time_spent_discarding_ms,
+                        stat.time_spent_discarding_ms,
+                        time_difference,
+                    )?,
+                    flushes_completed: per_minute_value_option(
+                        "flushes_completed",
+                        FieldKind::Counter,
+                        next_stat.flushes_completed,
+                        stat.flushes_completed,
+                        time_difference,
+                    )?,
+                    time_spent_flushing_ms: per_minute_value_option(
+                        "time_spent_flushing_ms",
+                        FieldKind::Counter,
+                        next_stat.time_spent_flushing_ms,
+                        stat.time_spent_flushing_ms,
+                        time_difference,
+                    )?,
                 },
             );
         }
 
         Ok(DiskStatsPerMinute { stats })
     }
+
+    /// Calculate the `iostat -x` style metrics of every device based on this measurement and a
+    /// measurement in the future.
+    pub fn calculate_io_stats(
+        &self,
+        next_measurement: &DiskStatsMeasurement,
+    ) -> Result<DiskIoStats> {
+        let time_difference =
+            calculate_time_difference(self.precise_time_ns, next_measurement.precise_time_ns)?;
+        if time_difference == 0 {
+            return Err(ProbeError::InvalidInput(
+                "Measurements were taken at the same time".to_owned(),
+            ));
+        }
+        let elapsed_ms = time_difference as f64 / 1_000_000.0;
+        let elapsed_seconds = elapsed_ms / 1000.0;
+
+        let mut io_stats = HashMap::new();
+
+        for (name, stat) in self.stats.iter() {
+            let next_stat = match next_measurement.stats.get(name) {
+                Some(stat) => stat,
+                None => {
+                    return Err(ProbeError::UnexpectedContent(format!(
+                        "{} is not present in the next measurement",
+                        name
+                    )))
+                }
+            };
+
+            let reads = counter_difference(
+                "reads_completed_successfully",
+                next_stat.reads_completed_successfully,
+                stat.reads_completed_successfully,
+            )?;
+            let writes = counter_difference(
+                "writes_completed",
+                next_stat.writes_completed,
+                stat.writes_completed,
+            )?;
+            let read_bytes =
+                counter_difference("sectors_read", next_stat.sectors_read, stat.sectors_read)?
+                    * 512;
+            let write_bytes = counter_difference(
+                "sectors_written",
+                next_stat.sectors_written,
+                stat.sectors_written,
+            )? * 512;
+            let time_reading_ms = counter_difference(
+                "time_spent_reading_ms",
+                next_stat.time_spent_reading_ms,
+                stat.time_spent_reading_ms,
+            )?;
+            let time_writing_ms = counter_difference(
+                "time_spent_writing_ms",
+                next_stat.time_spent_writing_ms,
+                stat.time_spent_writing_ms,
+            )?;
+            let time_doing_ios_ms = counter_difference(
+                "time_spent_doing_ios_ms",
+                next_stat.time_spent_doing_ios_ms,
+                stat.time_spent_doing_ios_ms,
+            )?;
+            let weighted_time_doing_ios_ms = counter_difference(
+                "weighted_time_spent_doing_ios_ms",
+                next_stat.weighted_time_spent_doing_ios_ms,
+                stat.weighted_time_spent_doing_ios_ms,
+            )?;
+
+            io_stats.insert(
+                name.to_owned(),
+                DiskIoStat {
+                    reads_per_second: (reads as f64 / elapsed_seconds) as f32,
+                    writes_per_second: (writes as f64 / elapsed_seconds) as f32,
+                    read_bytes_per_second: (read_bytes as f64 / elapsed_seconds) as f32,
+                    write_bytes_per_second: (write_bytes as f64 / elapsed_seconds) as f32,
+                    average_read_request_size: average(read_bytes, reads),
+                    average_write_request_size: average(write_bytes, writes),
+                    read_await_ms: average(time_reading_ms, reads),
+                    write_await_ms: average(time_writing_ms, writes),
+                    average_queue_size: (weighted_time_doing_ios_ms as f64 / elapsed_ms) as f32,
+                    // Devices serving requests in parallel can exceed 100%, iostat caps it too
+                    utilization_percentage: (time_doing_ios_ms as f64 / elapsed_ms * 100.0)
+                        .min(100.0) as f32,
+                },
+            );
+        }
+
+        Ok(io_stats)
+    }
+}
+
+#[inline]
+fn average(total: u64, count: u64) -> f32 {
+    if count == 0 {
+        return 0.0;
+    }
+    (total as f64 / count as f64) as f32
 }
 
 #[derive(Debug, PartialEq)]
 pub struct DiskStat {
+    pub major: u64,
+    pub minor: u64,
     pub reads_completed_successfully: u64,
     pub reads_merged: u64,
     pub sectors_read: u64,
@@ -122,9 +289,19 @@ pub struct DiskStat {
     pub writes_merged: u64,
     pub sectors_written: u64,
     pub time_spent_writing_ms: u64,
+    /// Gauge of the number of I/Os queued or being served, which isn't converted to a rate in
+    /// per-minute calculations
     pub ios_currently_in_progress: u64,
     pub time_spent_doing_ios_ms: u64,
     pub weighted_time_spent_doing_ios_ms: u64,
+    /// Discard stats, only available on kernel 4.18+
+    pub discards_completed: Option<u64>,
+    pub discards_merged: Option<u64>,
+    pub sectors_discarded: Option<u64>,
+    pub time_spent_discarding_ms: Option<u64>,
+    /// Flush stats, only available on kernel 5.5+
+    pub flushes_completed: Option<u64>,
+    pub time_spent_flushing_ms: Option<u64>,
 }
 
 impl DiskStat {
@@ -135,6 +312,10 @@ impl DiskStat {
     pub fn bytes_written(&self) -> u64 {
         self.sectors_written * 512
     }
+
+    pub fn bytes_discarded(&self) -> Option<u64> {
+        self.sectors_discarded.map(|sectors| sectors * 512)
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -142,17 +323,65 @@ pub struct DiskStatsPerMinute {
     pub stats: DiskStats,
 }
 
+pub type DiskIoStats = HashMap<String, DiskIoStat>;
+
+/// Metrics of a device between two measurements, matching the output of `iostat -x`
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiskIoStat {
+    /// Reads completed per second (`r/s`)
+    pub reads_per_second: f32,
+    /// Writes completed per second (`w/s`)
+    pub writes_per_second: f32,
+    /// Bytes read per second (`rkB/s`, which iostat reports in KB)
+    pub read_bytes_per_second: f32,
+    /// Bytes written per second (`wkB/s`, which iostat reports in KB)
+    pub write_bytes_per_second: f32,
+    /// Average size of a read in bytes (`rareq-sz`, which iostat reports in KB)
+    pub average_read_request_size: f32,
+    /// Average size of a write in bytes (`wareq-sz`, which iostat reports in KB)
+    pub average_write_request_size: f32,
+    /// Average time to serve a read, including the time spent queued (`r_await`)
+    pub read_await_ms: f32,
+    /// Average time to serve a write, including the time spent queued (`w_await`)
+    pub write_await_ms: f32,
+    /// Average number of requests queued or being served (`aqu-sz`)
+    pub average_queue_size: f32,
+    /// Percentage of time the device was busy serving requests (`%util`)
+    pub utilization_percentage: f32,
+}
+
+/// Which devices `read_filtered` reports
+#[derive(Debug, Default, PartialEq)]
+pub struct DiskStatsFilter {
+    /// Only report whole disks backed by hardware, skipping partitions and virtual devices such as
+    /// loop, ram, zram and device mapper devices
+    pub physical_disks_only: bool,
+    /// Report device mapper devices by their name, such as the LVM volume `vg0-root`, instead of
+    /// `dm-0`
+    pub resolve_device_mapper_names: bool,
+}
+
 #[cfg(target_os = "linux")]
 pub fn read() -> Result<DiskStatsMeasurement> {
-    os::read_and_parse_proc_diskstats(&Path::new("/proc/diskstats"))
+    os::read_and_parse_proc_diskstats(Path::new("/proc/diskstats"))
+}
+
+/// Read the disk stats of the devices matching the filter, using the device metadata from
+/// `/sys/block`.
+#[cfg(target_os = "linux")]
+pub fn read_filtered(filter: &DiskStatsFilter) -> Result<DiskStatsMeasurement> {
+    let measurement = read()?;
+    let devices = super::block_devices::read()?;
+    Ok(os::filter_measurement(measurement, &devices, filter))
 }
 
 #[cfg(target_os = "linux")]
-mod os {
+pub(crate) mod os {
+    use super::super::block_devices::BlockDevice;
     use super::super::{
         file_to_buf_reader, parse_u64, path_to_string, precise_time_ns, ProbeError, Result,
     };
-    use super::{DiskStat, DiskStatsMeasurement};
+    use super::{DiskStat, DiskStatsFilter, DiskStatsMeasurement};
     use std::collections::HashMap;
     use std::io::BufRead;
     use std::path::Path;
@@ -179,6 +408,8 @@ mod os {
             }
 
             let disk_stat = DiskStat {
+                major: parse_u64(segments[0])?,
+                minor: parse_u64(segments[1])?,
                 reads_completed_successfully: parse_u64(segments[3])?,
                 reads_merged: parse_u64(segments[4])?,
                 sectors_read: parse_u64(segments[5])?,
@@ -190,6 +421,12 @@ mod os {
                 ios_currently_in_progress: parse_u64(segments[11])?,
                 time_spent_doing_ios_ms: parse_u64(segments[12])?,
                 weighted_time_spent_doing_ios_ms: parse_u64(segments[13])?,
+                discards_completed: parse_optional_segment(&segments, 14)?,
+                discards_merged: parse_optional_segment(&segments, 15)?,
+                sectors_discarded: parse_optional_segment(&segments, 16)?,
+                time_spent_discarding_ms: parse_optional_segment(&segments, 17)?,
+                flushes_completed: parse_optional_segment(&segments, 18)?,
+                time_spent_flushing_ms: parse_optional_segment(&segments, 19)?,
             };
 
             out.stats.insert(segments[2].to_owned(), disk_stat);
@@ -197,13 +434,55 @@ mod os {
 
         Ok(out)
     }
+
+    pub fn filter_measurement(
+        measurement: DiskStatsMeasurement,
+        devices: &[BlockDevice],
+        filter: &DiskStatsFilter,
+    ) -> DiskStatsMeasurement {
+        let devices: HashMap<&str, &BlockDevice> = devices
+            .iter()
+            .map(|device| (device.name.as_str(), device))
+            .collect();
+
+        let stats = measurement
+            .stats
+            .into_iter()
+            .filter_map(|(name, stat)| {
+                let device = devices.get(name.as_str());
+                if filter.physical_disks_only && !device.map_or(false, |device| device.physical) {
+                    return None;
+                }
+
+                match device.and_then(|device| device.device_mapper_name.as_ref()) {
+                    Some(dm_name) if filter.resolve_device_mapper_names => {
+                        Some((dm_name.to_owned(), stat))
+                    }
+                    _ => Some((name, stat)),
+                }
+            })
+            .collect();
+
+        DiskStatsMeasurement {
+            precise_time_ns: measurement.precise_time_ns,
+            stats,
+        }
+    }
+
+    #[inline]
+    fn parse_optional_segment(segments: &[&str], index: usize) -> Result<Option<u64>> {
+        segments
+            .get(index)
+            .map(|segment| parse_u64(segment))
+            .transpose()
+    }
 }
 
 #[cfg(test)]
 #[cfg(target_os = "linux")]
 mod tests {
-    use super::os::read_and_parse_proc_diskstats;
-    use super::DiskStatsMeasurement;
+    use super::os::{filter_measurement, read_and_parse_proc_diskstats};
+    use super::{DiskIoStat, DiskStat, DiskStatsFilter, DiskStatsMeasurement};
     use crate::error::ProbeError;
     use std::collections::HashMap;
     use std::path::Path;
@@ -216,7 +495,7 @@ mod tests {
     #[test]
     fn test_read_and_parse_proc_diskstats() {
         let measurement =
-            read_and_parse_proc_diskstats(&Path::new("fixtures/linux/disk_stats/proc_diskstats"))
+            read_and_parse_proc_diskstats(Path::new("fixtures/linux/disk_stats/proc_diskstats"))
                 .unwrap();
 
         assert!(measurement.precise_time_ns > 0);
@@ -224,6 +503,8 @@ mod tests {
         assert_eq!(2, measurement.stats.len());
 
         let sda = measurement.stats.get("sda").unwrap();
+        assert_eq!(8, sda.major);
+        assert_eq!(0, sda.minor);
         assert_eq!(6185, sda.reads_completed_successfully);
         assert_eq!(9367, sda.reads_merged);
         assert_eq!(403272, sda.sectors_read);
@@ -252,11 +533,14 @@ mod tests {
         assert_eq!(0, sda1.ios_currently_in_progress);
         assert_eq!(930, sda1.time_spent_doing_ios_ms);
         assert_eq!(1140, sda1.weighted_time_spent_doing_ios_ms);
+        assert_eq!(None, sda1.discards_completed);
+        assert_eq!(None, sda1.bytes_discarded());
+        assert_eq!(None, sda1.flushes_completed);
     }
 
     #[test]
     fn test_read_and_parse_proc_diskstats_kernel_4_18_plus() {
-        let measurement = read_and_parse_proc_diskstats(&Path::new(
+        let measurement = read_and_parse_proc_diskstats(Path::new(
             "fixtures/linux/disk_stats/proc_diskstats_4_18",
         ))
         .unwrap();
@@ -279,6 +563,10 @@ mod tests {
         assert_eq!(0, sda.ios_currently_in_progress);
         assert_eq!(8960, sda.time_spent_doing_ios_ms);
         assert_eq!(24990, sda.weighted_time_spent_doing_ios_ms);
+        assert_eq!(Some(0), sda.discards_completed);
+        assert_eq!(Some(0), sda.bytes_discarded());
+        assert_eq!(None, sda.flushes_completed);
+        assert_eq!(None, sda.time_spent_flushing_ms);
 
         let sda1 = measurement.stats.get("sda1").unwrap();
         assert_eq!(483, sda1.reads_completed_successfully);
@@ -294,11 +582,12 @@ mod tests {
         assert_eq!(0, sda1.ios_currently_in_progress);
         assert_eq!(930, sda1.time_spent_doing_ios_ms);
         assert_eq!(1140, sda1.weighted_time_spent_doing_ios_ms);
+        assert_eq!(Some(0), sda1.discards_completed);
     }
 
     #[test]
     fn test_read_and_parse_proc_diskstats_kernel_5_5_plus() {
-        let measurement = read_and_parse_proc_diskstats(&Path::new(
+        let measurement = read_and_parse_proc_diskstats(Path::new(
             "fixtures/linux/disk_stats/proc_diskstats_5_5",
         ))
         .unwrap();
@@ -322,6 +611,8 @@ mod tests {
         assert_eq!(0, xvda.ios_currently_in_progress);
         assert_eq!(42381, xvda.time_spent_doing_ios_ms);
         assert_eq!(112571, xvda.weighted_time_spent_doing_ios_ms);
+        assert_eq!(Some(0), xvda.discards_completed);
+        assert_eq!(Some(0), xvda.flushes_completed);
 
         let xvdf = measurement.stats.get("xvdf").unwrap();
 
@@ -340,9 +631,44 @@ mod tests {
         assert_eq!(33450, xvdf.weighted_time_spent_doing_ios_ms);
     }
 
+    #[test]
+    fn test_read_and_parse_proc_diskstats_discards() {
+        let measurement = read_and_parse_proc_diskstats(Path::new(
+            "fixtures/linux/disk_stats/proc_diskstats_4_18_discard",
+        ))
+        .unwrap();
+
+        let sda = measurement.stats.get("sda").unwrap();
+        assert_eq!(6185, sda.reads_completed_successfully);
+        assert_eq!(Some(12), sda.discards_completed);
+        assert_eq!(Some(3), sda.discards_merged);
+        assert_eq!(Some(2048), sda.sectors_discarded);
+        assert_eq!(Some(1048576), sda.bytes_discarded());
+        assert_eq!(Some(40), sda.time_spent_discarding_ms);
+        assert_eq!(None, sda.flushes_completed);
+        assert_eq!(None, sda.time_spent_flushing_ms);
+    }
+
+    #[test]
+    fn test_read_and_parse_proc_diskstats_flushes() {
+        let measurement = read_and_parse_proc_diskstats(Path::new(
+            "fixtures/linux/disk_stats/proc_diskstats_5_5_flush",
+        ))
+        .unwrap();
+
+        let xvda = measurement.stats.get("xvda").unwrap();
+        assert_eq!(11514, xvda.reads_completed_successfully);
+        assert_eq!(Some(21), xvda.discards_completed);
+        assert_eq!(Some(2), xvda.discards_merged);
+        assert_eq!(Some(4096), xvda.sectors_discarded);
+        assert_eq!(Some(15), xvda.time_spent_discarding_ms);
+        assert_eq!(Some(130), xvda.flushes_completed);
+        assert_eq!(Some(84), xvda.time_spent_flushing_ms);
+    }
+
     #[test]
     fn test_read_and_parse_proc_diskstats_incomplete() {
-        match read_and_parse_proc_diskstats(&Path::new(
+        match read_and_parse_proc_diskstats(Path::new(
             "fixtures/linux/disk_stats/proc_diskstats_incomplete",
         )) {
             Err(ProbeError::UnexpectedContent(_)) => (),
@@ -352,7 +678,7 @@ mod tests {
 
     #[test]
     fn test_read_and_parse_proc_diskstats_garbage() {
-        match read_and_parse_proc_diskstats(&Path::new(
+        match read_and_parse_proc_diskstats(Path::new(
             "fixtures/linux/disk_stats/proc_diskstats_garbage",
         )) {
             Err(ProbeError::UnexpectedContent(_)) => (),
@@ -360,6 +686,70 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_device_names() {
+        let names = read_and_parse_proc_diskstats(Path::new(
+            "fixtures/linux/disk_stats/proc_diskstats_5_5",
+        ))
+        .unwrap()
+        .device_names();
+
+        assert_eq!(7, names.len());
+        assert_eq!("xvda", names.get(&(202, 0)).unwrap());
+        assert_eq!("xvdf1", names.get(&(202, 81)).unwrap());
+    }
+
+    #[test]
+    fn test_read_filtered() {
+        let measurement = super::read_filtered(&DiskStatsFilter {
+            physical_disks_only: true,
+            resolve_device_mapper_names: true,
+        })
+        .unwrap();
+        assert!(measurement.stats.len() <= super::read().unwrap().stats.len());
+    }
+
+    #[test]
+    fn test_filter_measurement() {
+        let filter = DiskStatsFilter::default();
+        let measurement = filter_measurement(helpers::measurement(), &helpers::devices(), &filter);
+
+        let mut names: Vec<&String> = measurement.stats.keys().collect();
+        names.sort();
+        assert_eq!(vec!["dm-0", "loop0", "sda", "sda1", "sdc"], names);
+    }
+
+    #[test]
+    fn test_filter_measurement_physical_disks_only() {
+        let filter = DiskStatsFilter {
+            physical_disks_only: true,
+            ..Default::default()
+        };
+        let measurement = filter_measurement(helpers::measurement(), &helpers::devices(), &filter);
+
+        // Devices which are not in `/sys/block` are skipped too
+        let names: Vec<&String> = measurement.stats.keys().collect();
+        assert_eq!(vec!["sda"], names);
+        assert_eq!(60_000_000_000, measurement.precise_time_ns);
+    }
+
+    #[test]
+    fn test_filter_measurement_resolve_device_mapper_names() {
+        let filter = DiskStatsFilter {
+            resolve_device_mapper_names: true,
+            ..Default::default()
+        };
+        let measurement = filter_measurement(helpers::measurement(), &helpers::devices(), &filter);
+
+        let mut names: Vec<&String> = measurement.stats.keys().collect();
+        names.sort();
+        assert_eq!(vec!["loop0", "sda", "sda1", "sdc", "vg0-root"], names);
+        assert_eq!(
+            &helpers::disk_stat(4),
+            measurement.stats.get("vg0-root").unwrap()
+        );
+    }
+
     #[test]
     fn test_calculate_per_minute_full_minute() {
         let mut stats1 = HashMap::new();
@@ -388,6 +778,12 @@ mod tests {
         assert_eq!(sda1.ios_currently_in_progress, 120);
         assert_eq!(sda1.time_spent_doing_ios_ms, 120);
         assert_eq!(sda1.weighted_time_spent_doing_ios_ms, 120);
+        assert_eq!(sda1.discards_completed, Some(120));
+        assert_eq!(sda1.discards_merged, Some(120));
+        assert_eq!(sda1.sectors_discarded, Some(120));
+        assert_eq!(sda1.time_spent_discarding_ms, Some(120));
+        assert_eq!(sda1.flushes_completed, Some(120));
+        assert_eq!(sda1.time_spent_flushing_ms, Some(120));
     }
 
     #[test]
@@ -415,9 +811,217 @@ mod tests {
         assert_eq!(sda1.writes_merged, 240);
         assert_eq!(sda1.sectors_written, 240);
         assert_eq!(sda1.time_spent_writing_ms, 240);
-        assert_eq!(sda1.ios_currently_in_progress, 240);
+        assert_eq!(sda1.ios_currently_in_progress, 120);
         assert_eq!(sda1.time_spent_doing_ios_ms, 240);
         assert_eq!(sda1.weighted_time_spent_doing_ios_ms, 240);
+        assert_eq!(sda1.discards_completed, Some(240));
+        assert_eq!(sda1.discards_merged, Some(240));
+        assert_eq!(sda1.sectors_discarded, Some(240));
+        assert_eq!(sda1.time_spent_discarding_ms, Some(240));
+        assert_eq!(sda1.flushes_completed, Some(240));
+        assert_eq!(sda1.time_spent_flushing_ms, Some(240));
+    }
+
+    #[test]
+    fn test_calculate_per_minute_shrinking_queue() {
+        let mut stats1 = HashMap::new();
+        stats1.insert(
+            "sda1".to_owned(),
+            DiskStat {
+                ios_currently_in_progress: 32,
+                ..helpers::disk_stat(0)
+            },
+        );
+        let measurement1 = DiskStatsMeasurement {
+            precise_time_ns: 60_000_000_000,
+            stats: stats1,
+        };
+        let mut stats2 = HashMap::new();
+        stats2.insert(
+            "sda1".to_owned(),
+            DiskStat {
+                ios_currently_in_progress: 4,
+                ..helpers::disk_stat(60)
+            },
+        );
+        let measurement2 = DiskStatsMeasurement {
+            precise_time_ns: 90_000_000_000,
+            stats: stats2,
+        };
+
+        let per_minute = measurement1.calculate_per_minute(&measurement2).unwrap();
+        let sda1 = per_minute.stats.get("sda1").unwrap();
+        assert_eq!(sda1.reads_completed_successfully, 120);
+        assert_eq!(sda1.ios_currently_in_progress, 4);
+    }
+
+    #[test]
+    fn test_calculate_per_minute_without_discard_and_flush() {
+        let mut stats1 = HashMap::new();
+        stats1.insert("sda1".to_owned(), helpers::disk_stat_without_optional(0));
+        let measurement1 = DiskStatsMeasurement {
+            precise_time_ns: 60_000_000_000,
+            stats: stats1,
+        };
+        let mut stats2 = HashMap::new();
+        stats2.insert("sda1".to_owned(), helpers::disk_stat_without_optional(120));
+        let measurement2 = DiskStatsMeasurement {
+            precise_time_ns: 120_000_000_000,
+            stats: stats2,
+        };
+
+        let per_minute = measurement1.calculate_per_minute(&measurement2).unwrap();
+        let sda1 = per_minute.stats.get("sda1").unwrap();
+        assert_eq!(sda1.reads_completed_successfully, 120);
+        assert_eq!(sda1.discards_completed, None);
+        assert_eq!(sda1.flushes_completed, None);
+    }
+
+    #[test]
+    fn test_calculate_per_minute_discard_missing_in_next() {
+        let mut stats1 = HashMap::new();
+        stats1.insert("sda1".to_owned(), helpers::disk_stat(0));
+        let measurement1 = DiskStatsMeasurement {
+            precise_time_ns: 60_000_000_000,
+            stats: stats1,
+        };
+        let mut stats2 = HashMap::new();
+        stats2.insert("sda1".to_owned(), helpers::disk_stat_without_optional(120));
+        let measurement2 = DiskStatsMeasurement {
+            precise_time_ns: 120_000_000_000,
+            stats: stats2,
+        };
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_io_stats() {
+        let mut stats1 = HashMap::new();
+        stats1.insert("sda1".to_owned(), helpers::disk_stat(1000));
+        let measurement1 = DiskStatsMeasurement {
+            precise_time_ns: 60_000_000_000,
+            stats: stats1,
+        };
+        let mut stats2 = HashMap::new();
+        stats2.insert(
+            "sda1".to_owned(),
+            DiskStat {
+                reads_completed_successfully: 1100,
+                sectors_read: 1800,
+                time_spent_reading_ms: 1500,
+                writes_completed: 1050,
+                sectors_written: 1400,
+                time_spent_writing_ms: 2000,
+                time_spent_doing_ios_ms: 3500,
+                weighted_time_spent_doing_ios_ms: 8500,
+                ..helpers::disk_stat(1000)
+            },
+        );
+        let measurement2 = DiskStatsMeasurement {
+            precise_time_ns: 70_000_000_000,
+            stats: stats2,
+        };
+
+        let io_stats = measurement1.calculate_io_stats(&measurement2).unwrap();
+        assert_eq!(
+            &DiskIoStat {
+                reads_per_second: 10.0,
+                writes_per_second: 5.0,
+                read_bytes_per_second: 40960.0,
+                write_bytes_per_second: 20480.0,
+                average_read_request_size: 4096.0,
+                average_write_request_size: 4096.0,
+                read_await_ms: 5.0,
+                write_await_ms: 20.0,
+                average_queue_size: 0.75,
+                utilization_percentage: 25.0,
+            },
+            io_stats.get("sda1").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_calculate_io_stats_idle() {
+        let mut stats1 = HashMap::new();
+        stats1.insert("sda1".to_owned(), helpers::disk_stat(1000));
+        let measurement1 = DiskStatsMeasurement {
+            precise_time_ns: 60_000_000_000,
+            stats: stats1,
+        };
+        let mut stats2 = HashMap::new();
+        stats2.insert("sda1".to_owned(), helpers::disk_stat(1000));
+        let measurement2 = DiskStatsMeasurement {
+            precise_time_ns: 120_000_000_000,
+            stats: stats2,
+        };
+
+        let io_stats = measurement1.calculate_io_stats(&measurement2).unwrap();
+        let sda1 = io_stats.get("sda1").unwrap();
+        // No division by zero without any requests
+        assert_eq!(0.0, sda1.average_read_request_size);
+        assert_eq!(0.0, sda1.read_await_ms);
+        assert_eq!(0.0, sda1.utilization_percentage);
+    }
+
+    #[test]
+    fn test_calculate_io_stats_utilization_capped() {
+        let mut stats1 = HashMap::new();
+        stats1.insert("sda1".to_owned(), helpers::disk_stat(0));
+        let measurement1 = DiskStatsMeasurement {
+            precise_time_ns: 60_000_000_000,
+            stats: stats1,
+        };
+        let mut stats2 = HashMap::new();
+        stats2.insert("sda1".to_owned(), helpers::disk_stat(2000));
+        let measurement2 = DiskStatsMeasurement {
+            precise_time_ns: 61_000_000_000,
+            stats: stats2,
+        };
+
+        let io_stats = measurement1.calculate_io_stats(&measurement2).unwrap();
+        assert_eq!(100.0, io_stats.get("sda1").unwrap().utilization_percentage);
+    }
+
+    #[test]
+    fn test_calculate_io_stats_same_time() {
+        let measurement1 = DiskStatsMeasurement {
+            precise_time_ns: 500,
+            stats: HashMap::new(),
+        };
+        let measurement2 = DiskStatsMeasurement {
+            precise_time_ns: 500,
+            stats: HashMap::new(),
+        };
+
+        match measurement1.calculate_io_stats(&measurement2) {
+            Err(ProbeError::InvalidInput(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_io_stats_values_lower() {
+        let mut stats1 = HashMap::new();
+        stats1.insert("sda1".to_owned(), helpers::disk_stat(500));
+        let measurement1 = DiskStatsMeasurement {
+            precise_time_ns: 500,
+            stats: stats1,
+        };
+        let mut stats2 = HashMap::new();
+        stats2.insert("sda1".to_owned(), helpers::disk_stat(400));
+        let measurement2 = DiskStatsMeasurement {
+            precise_time_ns: 600,
+            stats: stats2,
+        };
+
+        match measurement1.calculate_io_stats(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
     }
 
     #[test]
@@ -480,10 +1084,33 @@ mod tests {
     }
 
     mod helpers {
-        use super::super::DiskStat;
+        use super::super::super::block_devices::{self, BlockDevice};
+        use super::super::{DiskStat, DiskStatsMeasurement};
+        use std::collections::HashMap;
+        use std::path::Path;
+
+        pub fn devices() -> Vec<BlockDevice> {
+            let path = Path::new("fixtures/linux/sys/block");
+            block_devices::os::read_and_parse_block_devices(path).unwrap()
+        }
+
+        pub fn measurement() -> DiskStatsMeasurement {
+            let mut stats = HashMap::new();
+            stats.insert("sda".to_owned(), disk_stat(1));
+            stats.insert("sda1".to_owned(), disk_stat(2));
+            stats.insert("loop0".to_owned(), disk_stat(3));
+            stats.insert("dm-0".to_owned(), disk_stat(4));
+            stats.insert("sdc".to_owned(), disk_stat(5));
+            DiskStatsMeasurement {
+                precise_time_ns: 60_000_000_000,
+                stats,
+            }
+        }
 
         pub fn disk_stat(value: u64) -> DiskStat {
             DiskStat {
+                major: 8,
+                minor: 1,
                 reads_completed_successfully: value,
                 reads_merged: value,
                 sectors_read: value,
@@ -495,6 +1122,24 @@ mod tests {
                 ios_currently_in_progress: value,
                 time_spent_doing_ios_ms: value,
                 weighted_time_spent_doing_ios_ms: value,
+                discards_completed: Some(value),
+                discards_merged: Some(value),
+                sectors_discarded: Some(value),
+                time_spent_discarding_ms: Some(value),
+                flushes_completed: Some(value),
+                time_spent_flushing_ms: Some(value),
+            }
+        }
+
+        pub fn disk_stat_without_optional(value: u64) -> DiskStat {
+            DiskStat {
+                discards_completed: None,
+                discards_merged: None,
+                sectors_discarded: None,
+                time_spent_discarding_ms: None,
+                flushes_completed: None,
+                time_spent_flushing_ms: None,
+                ..disk_stat(value)
             }
         }
     }
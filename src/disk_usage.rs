@@ -1,4 +1,7 @@
-use super::Result;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use super::{precise_time_ns, Result};
 
 #[derive(Debug, PartialEq)]
 pub struct DiskUsage {
@@ -8,6 +11,16 @@ pub struct DiskUsage {
     pub one_k_blocks_free: u64,
     pub used_percentage: u32,
     pub mountpoint: String,
+    /// Size in bytes, not available when read using `df`
+    pub bytes: Option<u64>,
+    /// Used bytes, not available when read using `df`
+    pub bytes_used: Option<u64>,
+    /// Bytes available to unprivileged users, not available when read using `df`
+    pub bytes_free: Option<u64>,
+    /// Filesystem type, such as `ext4`, not available when read using `df`
+    pub fs_type: Option<String>,
+    /// Whether the filesystem is mounted read-only, not available when read using `df`
+    pub read_only: Option<bool>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -20,26 +33,399 @@ pub struct DiskInodeUsage {
     pub mountpoint: String,
 }
 
-/// Read the current usage of all disks
+/// Filesystem types skipped by `read` and `read_inodes`. These are backed by memory, by the
+/// layers of a container image or by a read-only image, and don't fill up like a disk does.
+pub const EXCLUDED_FS_TYPES: &[&str] = &["tmpfs", "overlay", "squashfs"];
+
+/// Read the current usage of all local disks, skipping the `EXCLUDED_FS_TYPES`. Falls back to
+/// `df` when the mounts can't be read, which doesn't skip any filesystem types. Mounts that can't
+/// be queried with `statvfs`, such as a mountpoint without read access or one that got unmounted
+/// in the meantime, are left out of the result.
 #[cfg(target_os = "linux")]
 pub fn read() -> Result<Vec<DiskUsage>> {
-    os::read()
+    os::read(EXCLUDED_FS_TYPES)
 }
 
-/// Read the current inode usage of all disks
+/// Read the current usage of all local disks, skipping the given filesystem types. Falls back to
+/// `df` when the mounts can't be read. Mounts that can't be queried are left out, like `read`
+/// does.
+#[cfg(target_os = "linux")]
+pub fn read_excluding(fs_types: &[&str]) -> Result<Vec<DiskUsage>> {
+    os::read(fs_types)
+}
+
+/// Read the current inode usage of all local disks, skipping the `EXCLUDED_FS_TYPES`. Falls back
+/// to `df` when the mounts can't be read, which doesn't skip any filesystem types. Mounts that
+/// can't be queried with `statvfs` are left out of the result, like `read` does.
 #[cfg(target_os = "linux")]
 pub fn read_inodes() -> Result<Vec<DiskInodeUsage>> {
-    os::read_inodes()
+    os::read_inodes(EXCLUDED_FS_TYPES)
+}
+
+/// Read the current inode usage of all local disks, skipping the given filesystem types. Falls
+/// back to `df` when the mounts can't be read. Mounts that can't be queried are left out, like
+/// `read` does.
+#[cfg(target_os = "linux")]
+pub fn read_inodes_excluding(fs_types: &[&str]) -> Result<Vec<DiskInodeUsage>> {
+    os::read_inodes(fs_types)
+}
+
+/// Estimate of when a disk fills up based on its usage trend
+#[derive(Debug, PartialEq)]
+pub struct DiskFullForecast {
+    pub mountpoint: String,
+    /// Growth of the usage per second, in bytes for disk usage and in inodes for inode usage.
+    /// Negative when the usage is shrinking.
+    pub fill_rate_per_second: f64,
+    /// Estimated time until the disk is full, `None` when the usage isn't growing
+    pub time_until_full: Option<Duration>,
+}
+
+/// Forecasts when disks fill up by fitting a linear regression through the usage samples of every
+/// mountpoint within a time window. Add samples regularly, such as every minute, with
+/// `add_usage` or `add_inode_usage`. A mountpoint which is missing from some samples, such as
+/// while it's unmounted, is forecast until all its samples fell out of the window.
+#[derive(Debug)]
+pub struct DiskFullForecaster {
+    window_ns: u64,
+    samples: HashMap<String, VecDeque<UsageSample>>,
+}
+
+#[derive(Debug)]
+struct UsageSample {
+    precise_time_ns: u64,
+    used: u64,
+    /// Usage at which the disk is full for unprivileged users
+    capacity: u64,
+}
+
+impl DiskFullForecaster {
+    /// Create a forecaster which uses the samples within the window, such as the last 6 hours.
+    /// A longer window is less sensitive to short bursts of writes.
+    pub fn new(window: Duration) -> DiskFullForecaster {
+        DiskFullForecaster {
+            window_ns: window.as_nanos() as u64,
+            samples: HashMap::new(),
+        }
+    }
+
+    /// Add the disk usage of all mountpoints as read now, such as by `read`.
+    pub fn add_usage(&mut self, usage: &[DiskUsage]) {
+        self.add_usage_at(precise_time_ns(), usage)
+    }
+
+    /// Add the disk usage of all mountpoints as read at a certain time, in nanoseconds since the
+    /// Unix epoch. Samples may be added out of time order.
+    pub fn add_usage_at(&mut self, precise_time_ns: u64, usage: &[DiskUsage]) {
+        let samples = usage.iter().map(|usage| {
+            // Usage read using `df` is only precise in KB
+            let used = usage.bytes_used.unwrap_or(usage.one_k_blocks_used * 1024);
+            let free = usage.bytes_free.unwrap_or(usage.one_k_blocks_free * 1024);
+            (usage.mountpoint.as_str(), used, used + free)
+        });
+        self.add_samples(precise_time_ns, samples);
+    }
+
+    /// Add the inode usage of all mountpoints as read now, such as by `read_inodes`.
+    pub fn add_inode_usage(&mut self, usage: &[DiskInodeUsage]) {
+        self.add_inode_usage_at(precise_time_ns(), usage)
+    }
+
+    /// Add the inode usage of all mountpoints as read at a certain time, in nanoseconds since the
+    /// Unix epoch. Samples may be added out of time order.
+    pub fn add_inode_usage_at(&mut self, precise_time_ns: u64, usage: &[DiskInodeUsage]) {
+        let samples = usage.iter().map(|usage| {
+            (
+                usage.mountpoint.as_str(),
+                usage.iused,
+                usage.iused + usage.ifree,
+            )
+        });
+        self.add_samples(precise_time_ns, samples);
+    }
+
+    /// Forecast every mountpoint with at least two samples at different times, ordered by
+    /// mountpoint. Use a separate forecaster for disk and inode usage.
+    pub fn forecast(&self) -> Vec<DiskFullForecast> {
+        let mut out: Vec<DiskFullForecast> = self
+            .samples
+            .iter()
+            .filter_map(|(mountpoint, samples)| forecast_samples(mountpoint, samples))
+            .collect();
+        out.sort_by(|a, b| a.mountpoint.cmp(&b.mountpoint));
+        out
+    }
+
+    fn add_samples<'a>(
+        &mut self,
+        precise_time_ns: u64,
+        samples: impl Iterator<Item = (&'a str, u64, u64)>,
+    ) {
+        for (mountpoint, used, capacity) in samples {
+            let samples = self
+                .samples
+                .entry(mountpoint.to_owned())
+                .or_insert_with(VecDeque::new);
+            // Keep the samples ordered by time, even when they're added out of order
+            let index = samples.partition_point(|sample| sample.precise_time_ns <= precise_time_ns);
+            samples.insert(
+                index,
+                UsageSample {
+                    precise_time_ns,
+                    used,
+                    capacity,
+                },
+            );
+        }
+
+        // Forget samples which fell out of the window, and with them disks which have been
+        // unmounted for longer than the window
+        let latest_time_ns = match self
+            .samples
+            .values()
+            .filter_map(|samples| samples.back())
+            .map(|sample| sample.precise_time_ns)
+            .max()
+        {
+            Some(latest_time_ns) => latest_time_ns,
+            None => return,
+        };
+        let window_start = latest_time_ns.saturating_sub(self.window_ns);
+        for samples in self.samples.values_mut() {
+            while samples
+                .front()
+                .map_or(false, |sample| sample.precise_time_ns < window_start)
+            {
+                samples.pop_front();
+            }
+        }
+        self.samples.retain(|_, samples| !samples.is_empty());
+    }
+}
+
+/// Fit a line through the samples with the least squares method, its slope is the fill rate
+fn forecast_samples(mountpoint: &str, samples: &VecDeque<UsageSample>) -> Option<DiskFullForecast> {
+    let latest = samples.back()?;
+    let first_time_ns = samples.front()?.precise_time_ns;
+
+    let count = samples.len() as f64;
+    // Relative to the first sample to keep the precision of the floats
+    let points: Vec<(f64, f64)> = samples
+        .iter()
+        .map(|sample| {
+            (
+                sample.precise_time_ns.saturating_sub(first_time_ns) as f64 / 1_000_000_000.0,
+                sample.used as f64,
+            )
+        })
+        .collect();
+    let mean_time = points.iter().map(|(time, _)| time).sum::<f64>() / count;
+    let mean_used = points.iter().map(|(_, used)| used).sum::<f64>() / count;
+
+    let mut covariance = 0.0;
+    let mut variance = 0.0;
+    for (time, used) in points.iter() {
+        covariance += (time - mean_time) * (used - mean_used);
+        variance += (time - mean_time) * (time - mean_time);
+    }
+    if variance == 0.0 {
+        return None;
+    }
+    let fill_rate_per_second = covariance / variance;
+
+    let time_until_full = if fill_rate_per_second > 0.0 {
+        let remaining = latest.capacity.saturating_sub(latest.used) as f64;
+        // Too far in the future to represent when the disk barely grows
+        Duration::try_from_secs_f64(remaining / fill_rate_per_second).ok()
+    } else {
+        None
+    };
+
+    Some(DiskFullForecast {
+        mountpoint: mountpoint.to_owned(),
+        fill_rate_per_second,
+        time_until_full,
+    })
 }
 
 #[cfg(target_os = "linux")]
 mod os {
+    use super::super::mounts::{self, Mount};
     use super::super::{parse_u64, ProbeError, Result};
     use super::{DiskInodeUsage, DiskUsage};
+    use std::collections::{HashMap, HashSet};
+    use std::ffi::CString;
+    use std::io;
+    use std::mem;
     use std::process::Command;
 
+    // Network filesystems, these are excluded like `df --local` does
+    const REMOTE_FS_TYPES: &[&str] = &[
+        "nfs",
+        "nfs4",
+        "cifs",
+        "smb3",
+        "smbfs",
+        "ncpfs",
+        "afs",
+        "coda",
+        "ceph",
+        "glusterfs",
+        "lustre",
+        "9p",
+        "fuse.sshfs",
+    ];
+
+    /// Block and inode counts of a filesystem as reported by `statvfs`
+    #[derive(Debug, PartialEq)]
+    pub struct FilesystemStats {
+        pub fragment_size: u64,
+        pub blocks: u64,
+        pub blocks_free: u64,
+        pub blocks_available: u64,
+        pub files: u64,
+        pub files_free: u64,
+    }
+
     #[inline]
-    pub fn read() -> Result<Vec<DiskUsage>> {
+    pub fn read(excluded_fs_types: &[&str]) -> Result<Vec<DiskUsage>> {
+        match read_local_mounts(excluded_fs_types) {
+            Ok(mounts) => Ok(mounts
+                .iter()
+                .filter_map(|mount| {
+                    // A single unreadable mount shouldn't hide the usage of all other disks
+                    let stats = statvfs(&mount.mountpoint).ok()?;
+                    disk_usage(mount, &stats)
+                })
+                .collect()),
+            Err(_) => read_df(),
+        }
+    }
+
+    #[inline]
+    pub fn read_inodes(excluded_fs_types: &[&str]) -> Result<Vec<DiskInodeUsage>> {
+        match read_local_mounts(excluded_fs_types) {
+            Ok(mounts) => Ok(mounts
+                .iter()
+                .filter_map(|mount| {
+                    let stats = statvfs(&mount.mountpoint).ok()?;
+                    disk_inode_usage(mount, &stats)
+                })
+                .collect()),
+            Err(_) => read_df_inodes(),
+        }
+    }
+
+    #[inline]
+    fn read_local_mounts(excluded_fs_types: &[&str]) -> Result<Vec<Mount>> {
+        let mounts = mounts::read()?;
+        Ok(local_mounts(mounts, excluded_fs_types))
+    }
+
+    /// Filter out network filesystems, the excluded filesystem types, mounts hidden by a later
+    /// mount on the same mountpoint and the mounts of a device that is already mounted elsewhere,
+    /// such as bind mounts.
+    pub fn local_mounts(mounts: Vec<Mount>, excluded_fs_types: &[&str]) -> Vec<Mount> {
+        let last_mounts: HashMap<String, usize> = mounts
+            .iter()
+            .enumerate()
+            .map(|(index, mount)| (mount.mountpoint.clone(), index))
+            .collect();
+        let mut devices = HashSet::new();
+
+        mounts
+            .into_iter()
+            .enumerate()
+            .filter(|(index, mount)| last_mounts.get(&mount.mountpoint) == Some(index))
+            .map(|(_, mount)| mount)
+            .filter(|mount| !REMOTE_FS_TYPES.contains(&mount.fs_type.as_str()))
+            .filter(|mount| !excluded_fs_types.contains(&mount.fs_type.as_str()))
+            .filter(|mount| devices.insert((mount.major, mount.minor)))
+            .collect()
+    }
+
+    // The types of the `statvfs` fields differ per platform
+    #[allow(clippy::unnecessary_cast)]
+    fn statvfs(mountpoint: &str) -> Result<FilesystemStats> {
+        let c_path = CString::new(mountpoint).map_err(|_| {
+            ProbeError::InvalidInput(format!("Mountpoint '{}' contains a nul byte", mountpoint))
+        })?;
+
+        let mut stat = mem::MaybeUninit::<libc::statvfs>::uninit();
+        let result = unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) };
+        if result != 0 {
+            return Err(ProbeError::IO(
+                io::Error::last_os_error(),
+                mountpoint.to_owned(),
+            ));
+        }
+        let stat = unsafe { stat.assume_init() };
+
+        Ok(FilesystemStats {
+            fragment_size: stat.f_frsize as u64,
+            blocks: stat.f_blocks as u64,
+            blocks_free: stat.f_bfree as u64,
+            blocks_available: stat.f_bavail as u64,
+            files: stat.f_files as u64,
+            files_free: stat.f_ffree as u64,
+        })
+    }
+
+    /// Calculate the disk usage the same way `df` does. Returns `None` for pseudo filesystems
+    /// without any blocks, which `df` doesn't show either.
+    pub fn disk_usage(mount: &Mount, stats: &FilesystemStats) -> Option<DiskUsage> {
+        if stats.blocks == 0 {
+            return None;
+        }
+
+        let bytes = stats.blocks * stats.fragment_size;
+        let bytes_used = stats.blocks.saturating_sub(stats.blocks_free) * stats.fragment_size;
+        let bytes_free = stats.blocks_available * stats.fragment_size;
+
+        Some(DiskUsage {
+            filesystem: parse_filesystem(&mount.source),
+            one_k_blocks: bytes / 1024,
+            one_k_blocks_used: bytes_used / 1024,
+            one_k_blocks_free: bytes_free / 1024,
+            used_percentage: percentage_rounded_up(bytes_used, bytes_used + bytes_free),
+            mountpoint: mount.mountpoint.to_owned(),
+            bytes: Some(bytes),
+            bytes_used: Some(bytes_used),
+            bytes_free: Some(bytes_free),
+            fs_type: Some(mount.fs_type.to_owned()),
+            read_only: Some(mount.is_read_only()),
+        })
+    }
+
+    /// Calculate the inode usage the same way `df -i` does. Returns `None` for filesystems
+    /// without a fixed number of inodes, for which `df` reports a dash as percentage.
+    pub fn disk_inode_usage(mount: &Mount, stats: &FilesystemStats) -> Option<DiskInodeUsage> {
+        if stats.files == 0 {
+            return None;
+        }
+
+        let iused = stats.files.saturating_sub(stats.files_free);
+
+        Some(DiskInodeUsage {
+            filesystem: parse_filesystem(&mount.source),
+            inodes: stats.files,
+            iused,
+            ifree: stats.files_free,
+            iused_percentage: percentage_rounded_up(iused, iused + stats.files_free),
+            mountpoint: mount.mountpoint.to_owned(),
+        })
+    }
+
+    #[inline]
+    fn percentage_rounded_up(used: u64, total: u64) -> u32 {
+        if total == 0 {
+            return 0;
+        }
+        ((used * 100 + total - 1) / total) as u32
+    }
+
+    #[inline]
+    fn read_df() -> Result<Vec<DiskUsage>> {
         let mut out: Vec<DiskUsage> = Vec::new();
         let local_out = match disk_fs_local_raw(Some(&["--local"])) {
             Ok(o) => o,
@@ -59,6 +445,11 @@ mod os {
                 one_k_blocks_free: parse_u64(segment[3])?,
                 used_percentage: parse_percentage_segment(segment[4])?,
                 mountpoint: segment[5].to_string(),
+                bytes: None,
+                bytes_used: None,
+                bytes_free: None,
+                fs_type: None,
+                read_only: None,
             };
 
             out.push(usage);
@@ -68,7 +459,7 @@ mod os {
     }
 
     #[inline]
-    pub fn read_inodes() -> Result<Vec<DiskInodeUsage>> {
+    fn read_df_inodes() -> Result<Vec<DiskInodeUsage>> {
         let inodes_out = disk_fs_inodes_raw()?;
         parse_df_inodes_output(parse_df_output(&inodes_out)?)
     }
@@ -104,7 +495,7 @@ mod os {
         // Sometimes the filesystem is on a separate line
         let mut filesystem_on_previous_line: Option<&str> = None;
 
-        for line in output.split("\n").skip(1) {
+        for line in output.split('\n').skip(1) {
             let mut segments: Vec<&str> = line.split_whitespace().collect();
 
             match segments.len() {
@@ -203,7 +594,9 @@ mod os {
 mod tests {
     use super::super::file_to_string;
     use super::super::ProbeError;
+    use super::{DiskFullForecaster, DiskInodeUsage};
     use std::path::Path;
+    use std::time::Duration;
 
     #[test]
     fn test_read_disks() {
@@ -211,6 +604,106 @@ mod tests {
         assert!(!super::read().unwrap().is_empty());
     }
 
+    #[test]
+    fn test_read_disk_inodes() {
+        assert!(super::read_inodes().is_ok());
+    }
+
+    #[test]
+    fn test_local_mounts() {
+        let mountpoints = helpers::local_mountpoints(&[]);
+
+        // Network filesystems, hidden mounts and bind mounts of an already listed device are
+        // skipped
+        assert_eq!(
+            vec![
+                "/",
+                "/proc",
+                "/dev",
+                "/dev/pts",
+                "/sys",
+                "/sys/fs/cgroup",
+                "/etc/resolv.conf",
+                "/mnt/my data",
+                "/dev/shm",
+            ],
+            mountpoints
+        );
+    }
+
+    #[test]
+    fn test_local_mounts_excluded_fs_types() {
+        let mountpoints = helpers::local_mountpoints(super::EXCLUDED_FS_TYPES);
+
+        assert_eq!(
+            vec![
+                "/proc",
+                "/dev/pts",
+                "/sys",
+                "/sys/fs/cgroup",
+                "/etc/resolv.conf",
+                "/mnt/my data",
+            ],
+            mountpoints
+        );
+    }
+
+    #[test]
+    fn test_disk_usage() {
+        let usage = super::os::disk_usage(&helpers::mount(), &helpers::stats(1000, 0)).unwrap();
+
+        assert_eq!(
+            super::DiskUsage {
+                filesystem: Some("/dev/sda1".to_owned()),
+                one_k_blocks: 4000,
+                one_k_blocks_used: 2400,
+                one_k_blocks_free: 1400,
+                used_percentage: 64, // rounded up like df does
+                mountpoint: "/".to_owned(),
+                bytes: Some(4_096_000),
+                bytes_used: Some(2_457_600),
+                bytes_free: Some(1_433_600),
+                fs_type: Some("ext4".to_owned()),
+                read_only: Some(false),
+            },
+            usage
+        );
+    }
+
+    #[test]
+    fn test_disk_usage_without_blocks() {
+        assert_eq!(
+            None,
+            super::os::disk_usage(&helpers::mount(), &helpers::stats(0, 0))
+        );
+    }
+
+    #[test]
+    fn test_disk_inode_usage() {
+        let usage =
+            super::os::disk_inode_usage(&helpers::mount(), &helpers::stats(1000, 1000)).unwrap();
+
+        assert_eq!(
+            super::DiskInodeUsage {
+                filesystem: Some("/dev/sda1".to_owned()),
+                inodes: 1000,
+                iused: 750,
+                ifree: 250,
+                iused_percentage: 75,
+                mountpoint: "/".to_owned(),
+            },
+            usage
+        );
+    }
+
+    #[test]
+    fn test_disk_inode_usage_without_inodes() {
+        assert_eq!(
+            None,
+            super::os::disk_inode_usage(&helpers::mount(), &helpers::stats(1000, 0))
+        );
+    }
+
     #[test]
     fn test_parse_df_output() {
         let expected = vec![
@@ -284,4 +777,261 @@ mod tests {
             }]
         );
     }
+
+    #[test]
+    fn test_forecast() {
+        let mut forecaster = DiskFullForecaster::new(Duration::from_secs(3600));
+        for minute in 0..5 {
+            // Growing by 1 MB per minute with 10 MB left at the start
+            let used = 50_000_000 + minute * 1_000_000;
+            forecaster.add_usage_at(
+                helpers::minutes_ns(minute),
+                &[
+                    helpers::usage("/", used, 60_000_000 - used),
+                    helpers::usage("/data", 1_000, 9_000),
+                ],
+            );
+        }
+
+        let forecasts = forecaster.forecast();
+        assert_eq!(2, forecasts.len());
+
+        assert_eq!("/", forecasts[0].mountpoint);
+        assert!((forecasts[0].fill_rate_per_second - 16_666.666).abs() < 0.01);
+        // 6 MB left after 4 minutes
+        assert_eq!(Some(Duration::from_secs(360)), forecasts[0].time_until_full);
+
+        // Not growing
+        assert_eq!("/data", forecasts[1].mountpoint);
+        assert_eq!(0.0, forecasts[1].fill_rate_per_second);
+        assert_eq!(None, forecasts[1].time_until_full);
+    }
+
+    #[test]
+    fn test_forecast_shrinking() {
+        let mut forecaster = DiskFullForecaster::new(Duration::from_secs(3600));
+        forecaster.add_usage_at(helpers::minutes_ns(0), &[helpers::usage("/", 6_000, 4_000)]);
+        forecaster.add_usage_at(helpers::minutes_ns(1), &[helpers::usage("/", 5_400, 4_600)]);
+
+        let forecasts = forecaster.forecast();
+        assert_eq!(-10.0, forecasts[0].fill_rate_per_second);
+        assert_eq!(None, forecasts[0].time_until_full);
+    }
+
+    #[test]
+    fn test_forecast_not_enough_samples() {
+        let mut forecaster = DiskFullForecaster::new(Duration::from_secs(3600));
+        assert!(forecaster.forecast().is_empty());
+
+        forecaster.add_usage_at(helpers::minutes_ns(0), &[helpers::usage("/", 6_000, 4_000)]);
+        assert!(forecaster.forecast().is_empty());
+
+        // Samples at the same time don't show a trend
+        forecaster.add_usage_at(helpers::minutes_ns(0), &[helpers::usage("/", 7_000, 3_000)]);
+        assert!(forecaster.forecast().is_empty());
+    }
+
+    #[test]
+    fn test_forecast_window() {
+        let mut forecaster = DiskFullForecaster::new(Duration::from_secs(120));
+        // A burst of writes which falls out of the window
+        forecaster.add_usage_at(helpers::minutes_ns(0), &[helpers::usage("/", 0, 10_000)]);
+        forecaster.add_usage_at(helpers::minutes_ns(1), &[helpers::usage("/", 6_000, 4_000)]);
+        forecaster.add_usage_at(helpers::minutes_ns(2), &[helpers::usage("/", 6_060, 3_940)]);
+        forecaster.add_usage_at(helpers::minutes_ns(3), &[helpers::usage("/", 6_120, 3_880)]);
+
+        let forecasts = forecaster.forecast();
+        assert_eq!(1.0, forecasts[0].fill_rate_per_second);
+        assert_eq!(
+            Some(Duration::from_secs(3_880)),
+            forecasts[0].time_until_full
+        );
+    }
+
+    #[test]
+    fn test_forecast_unmounted() {
+        let mut forecaster = DiskFullForecaster::new(Duration::from_secs(120));
+        forecaster.add_usage_at(
+            helpers::minutes_ns(0),
+            &[
+                helpers::usage("/", 6_000, 4_000),
+                helpers::usage("/mnt", 6_000, 4_000),
+            ],
+        );
+        forecaster.add_usage_at(helpers::minutes_ns(1), &[helpers::usage("/", 6_600, 3_400)]);
+        forecaster.add_usage_at(
+            helpers::minutes_ns(2),
+            &[
+                helpers::usage("/", 7_200, 2_800),
+                helpers::usage("/mnt", 6_120, 3_880),
+            ],
+        );
+
+        // Missing from a sample doesn't forget the history of /mnt
+        let forecasts = forecaster.forecast();
+        assert_eq!(2, forecasts.len());
+        assert_eq!("/mnt", forecasts[1].mountpoint);
+        assert_eq!(1.0, forecasts[1].fill_rate_per_second);
+
+        // Until its samples fall out of the window
+        forecaster.add_usage_at(helpers::minutes_ns(3), &[helpers::usage("/", 7_800, 2_200)]);
+        forecaster.add_usage_at(helpers::minutes_ns(5), &[helpers::usage("/", 9_000, 1_000)]);
+        let mountpoints: Vec<String> = forecaster
+            .forecast()
+            .into_iter()
+            .map(|forecast| forecast.mountpoint)
+            .collect();
+        assert_eq!(vec!["/"], mountpoints);
+        assert_eq!(1, forecaster.samples.len());
+    }
+
+    #[test]
+    fn test_forecast_out_of_order() {
+        let mut forecaster = DiskFullForecaster::new(Duration::from_secs(3600));
+        forecaster.add_usage_at(
+            helpers::minutes_ns(10),
+            &[helpers::usage("/", 6_600, 3_400)],
+        );
+        forecaster.add_usage_at(
+            helpers::minutes_ns(12),
+            &[helpers::usage("/", 7_800, 2_200)],
+        );
+        // Older than the first sample
+        forecaster.add_usage_at(helpers::minutes_ns(9), &[helpers::usage("/", 6_000, 4_000)]);
+
+        let forecasts = forecaster.forecast();
+        assert_eq!(10.0, forecasts[0].fill_rate_per_second);
+        assert_eq!(Some(Duration::from_secs(220)), forecasts[0].time_until_full);
+    }
+
+    #[test]
+    fn test_forecast_out_of_order_outside_window() {
+        let mut forecaster = DiskFullForecaster::new(Duration::from_secs(120));
+        forecaster.add_usage_at(
+            helpers::minutes_ns(10),
+            &[helpers::usage("/", 6_000, 4_000)],
+        );
+        forecaster.add_usage_at(
+            helpers::minutes_ns(11),
+            &[helpers::usage("/", 6_600, 3_400)],
+        );
+        // Too old to be used
+        forecaster.add_usage_at(helpers::minutes_ns(1), &[helpers::usage("/", 0, 10_000)]);
+
+        let forecasts = forecaster.forecast();
+        assert_eq!(10.0, forecasts[0].fill_rate_per_second);
+        assert_eq!(2, forecaster.samples["/"].len());
+    }
+
+    #[test]
+    fn test_forecast_barely_growing() {
+        let mut forecaster = DiskFullForecaster::new(Duration::from_nanos(u64::MAX));
+        forecaster.add_usage_at(0, &[helpers::usage("/", 0, u64::MAX)]);
+        forecaster.add_usage_at(u64::MAX, &[helpers::usage("/", 1, u64::MAX - 1)]);
+
+        let forecasts = forecaster.forecast();
+        assert!(forecasts[0].fill_rate_per_second > 0.0);
+        assert_eq!(None, forecasts[0].time_until_full);
+    }
+
+    #[test]
+    fn test_forecast_inodes() {
+        let mut forecaster = DiskFullForecaster::new(Duration::from_secs(3600));
+        for minute in 0..3 {
+            forecaster.add_inode_usage_at(
+                helpers::minutes_ns(minute),
+                &[DiskInodeUsage {
+                    filesystem: Some("/dev/sda1".to_owned()),
+                    inodes: 10_000,
+                    iused: 1_000 + minute * 600,
+                    ifree: 9_000 - minute * 600,
+                    iused_percentage: 10,
+                    mountpoint: "/".to_owned(),
+                }],
+            );
+        }
+
+        let forecasts = forecaster.forecast();
+        assert_eq!(10.0, forecasts[0].fill_rate_per_second);
+        assert_eq!(Some(Duration::from_secs(780)), forecasts[0].time_until_full);
+    }
+
+    #[test]
+    fn test_forecast_df_usage() {
+        let mut forecaster = DiskFullForecaster::new(Duration::from_secs(3600));
+        for minute in 0..2 {
+            let mut usage = helpers::usage("/", 0, 0);
+            usage.one_k_blocks_used = 100 + minute * 60;
+            usage.one_k_blocks_free = 900 - minute * 60;
+            usage.bytes_used = None;
+            usage.bytes_free = None;
+            forecaster.add_usage_at(helpers::minutes_ns(minute), &[usage]);
+        }
+
+        let forecasts = forecaster.forecast();
+        assert_eq!(1024.0, forecasts[0].fill_rate_per_second);
+        assert_eq!(Some(Duration::from_secs(840)), forecasts[0].time_until_full);
+    }
+
+    mod helpers {
+        use super::super::super::mounts::{self, Mount};
+        use super::super::os::{local_mounts, FilesystemStats};
+        use super::super::DiskUsage;
+        use std::path::Path;
+
+        pub fn mount() -> Mount {
+            Mount {
+                mount_id: 22,
+                parent_id: 1,
+                major: 8,
+                minor: 1,
+                root: "/".to_owned(),
+                mountpoint: "/".to_owned(),
+                mount_options: vec!["rw".to_owned(), "relatime".to_owned()],
+                fs_type: "ext4".to_owned(),
+                source: "/dev/sda1".to_owned(),
+                super_options: vec!["rw".to_owned()],
+            }
+        }
+
+        pub fn local_mountpoints(excluded_fs_types: &[&str]) -> Vec<String> {
+            let path = Path::new("fixtures/linux/mounts/mountinfo");
+            let mounts = mounts::os::read_and_parse_mountinfo(path).unwrap();
+            local_mounts(mounts, excluded_fs_types)
+                .into_iter()
+                .map(|mount| mount.mountpoint)
+                .collect()
+        }
+
+        pub fn minutes_ns(minutes: u64) -> u64 {
+            1_600_000_000_000_000_000 + minutes * 60_000_000_000
+        }
+
+        pub fn usage(mountpoint: &str, bytes_used: u64, bytes_free: u64) -> DiskUsage {
+            DiskUsage {
+                filesystem: Some("/dev/sda1".to_owned()),
+                one_k_blocks: (bytes_used + bytes_free) / 1024,
+                one_k_blocks_used: bytes_used / 1024,
+                one_k_blocks_free: bytes_free / 1024,
+                used_percentage: 0,
+                mountpoint: mountpoint.to_owned(),
+                bytes: Some(bytes_used + bytes_free),
+                bytes_used: Some(bytes_used),
+                bytes_free: Some(bytes_free),
+                fs_type: Some("ext4".to_owned()),
+                read_only: Some(false),
+            }
+        }
+
+        pub fn stats(blocks: u64, files: u64) -> FilesystemStats {
+            FilesystemStats {
+                fragment_size: 4096,
+                blocks,
+                blocks_free: blocks * 2 / 5,
+                blocks_available: blocks * 7 / 20,
+                files,
+                files_free: files / 4,
+            }
+        }
+    }
 }
@@ -0,0 +1,396 @@
+use super::{calculate_time_difference, per_minute_counters_per_cpu, Result};
+use std::collections::HashMap;
+
+pub type Interrupts = HashMap<String, Interrupt>;
+
+/// Measurement of interrupt counts at a certain time.
+#[derive(Debug, PartialEq)]
+pub struct InterruptsMeasurement {
+    pub precise_time_ns: u64,
+    pub interrupts: Interrupts,
+}
+
+impl InterruptsMeasurement {
+    /// Calculate the interrupts per minute based on this measurement and a measurement in the
+    /// future. It is advisable to make the next measurement roughly a minute from this one for the
+    /// most reliable result.
+    pub fn calculate_per_minute(
+        &self,
+        next_measurement: &InterruptsMeasurement,
+    ) -> Result<InterruptsPerMinute> {
+        let time_difference =
+            calculate_time_difference(self.precise_time_ns, next_measurement.precise_time_ns)?;
+
+        let interrupts = per_minute_counters_per_cpu(
+            &next_measurement.interrupts,
+            &self.interrupts,
+            time_difference,
+            |interrupt| &interrupt.per_cpu,
+            |next_interrupt, per_cpu| Interrupt {
+                per_cpu,
+                description: next_interrupt.description.clone(),
+                devices: next_interrupt.devices.clone(),
+            },
+        )?;
+
+        Ok(InterruptsPerMinute { interrupts })
+    }
+}
+
+/// Interrupt counts of a single IRQ, per CPU.
+#[derive(Debug, PartialEq)]
+pub struct Interrupt {
+    pub per_cpu: Vec<u64>,
+    /// Everything listed after the counts, such as the interrupt chip or a description
+    /// for architecture specific interrupts.
+    pub description: Option<String>,
+    /// Names of the devices handling this IRQ, only present for numbered IRQs.
+    pub devices: Vec<String>,
+}
+
+impl Interrupt {
+    pub fn total(&self) -> u64 {
+        self.per_cpu.iter().sum()
+    }
+}
+
+/// Interrupts for a certain minute, calculated based on two measurements.
+#[derive(Debug, PartialEq)]
+pub struct InterruptsPerMinute {
+    pub interrupts: Interrupts,
+}
+
+/// Read the current interrupt counts of the system.
+#[cfg(target_os = "linux")]
+pub fn read() -> Result<InterruptsMeasurement> {
+    os::read()
+}
+
+#[cfg(target_os = "linux")]
+mod os {
+    use std::io::{self, BufRead};
+    use std::path::Path;
+
+    use super::super::{file_to_buf_reader, path_to_string, precise_time_ns, Result};
+    use super::{Interrupt, Interrupts, InterruptsMeasurement};
+    use crate::error::ProbeError;
+
+    // Trigger types the kernel prints after the hardware IRQ number, e.g. `2-edge` on x86 or
+    // `27 Level` on ARM
+    const TRIGGER_TYPES: [&str; 3] = ["edge", "level", "fasteoi"];
+
+    #[inline]
+    pub fn read() -> Result<InterruptsMeasurement> {
+        read_and_parse_interrupts(Path::new("/proc/interrupts"))
+    }
+
+    #[inline]
+    pub fn read_and_parse_interrupts(path: &Path) -> Result<InterruptsMeasurement> {
+        let reader = file_to_buf_reader(path)?;
+
+        let precise_time_ns = precise_time_ns();
+
+        let line_result: io::Result<Vec<String>> = reader.lines().collect();
+        let lines = line_result.map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
+
+        let cpu_count = match lines.first() {
+            Some(header) if header.trim_start().starts_with("CPU") => {
+                header.split_whitespace().count()
+            }
+            _ => {
+                return Err(ProbeError::UnexpectedContent(
+                    "CPU header not found".to_owned(),
+                ))
+            }
+        };
+
+        let mut interrupts = Interrupts::new();
+        for line in &lines[1..] {
+            let segments: Vec<&str> = line.split_whitespace().collect();
+            if segments.is_empty() {
+                continue;
+            }
+            let name = segments[0].trim_end_matches(':').to_owned();
+
+            // Some interrupts, such as `ERR`, only have a single count for all CPUs.
+            let per_cpu: Vec<u64> = segments[1..]
+                .iter()
+                .take(cpu_count)
+                .map_while(|segment| segment.parse().ok())
+                .collect();
+            if per_cpu.is_empty() {
+                return Err(ProbeError::UnexpectedContent(format!(
+                    "No counts found for '{}'",
+                    name
+                )));
+            }
+
+            let rest = &segments[1 + per_cpu.len()..];
+            let description = if rest.is_empty() {
+                None
+            } else {
+                Some(rest.join(" "))
+            };
+
+            let devices = if name.parse::<u64>().is_ok() {
+                parse_devices(rest)
+            } else {
+                Vec::new()
+            };
+
+            interrupts.insert(
+                name,
+                Interrupt {
+                    per_cpu,
+                    description,
+                    devices,
+                },
+            );
+        }
+
+        Ok(InterruptsMeasurement {
+            precise_time_ns,
+            interrupts,
+        })
+    }
+
+    /// Get the device names listed after the chip and trigger type, e.g.
+    /// `IO-APIC 9-fasteoi acpi, i801_smbus`.
+    #[inline]
+    fn parse_devices(segments: &[&str]) -> Vec<String> {
+        let trigger_position = segments.iter().position(|segment| {
+            segment.rsplit('-').next().map_or(false, |trigger| {
+                TRIGGER_TYPES
+                    .iter()
+                    .any(|trigger_type| trigger.eq_ignore_ascii_case(trigger_type))
+            })
+        });
+
+        match trigger_position {
+            Some(position) => segments[position + 1..]
+                .join(" ")
+                .split(',')
+                .map(|device| device.trim().to_owned())
+                .filter(|device| !device.is_empty())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::super::ProbeError;
+    use super::{Interrupt, Interrupts, InterruptsMeasurement};
+    use std::path::Path;
+
+    #[test]
+    fn test_read_interrupts() {
+        assert!(super::read().is_ok());
+        assert!(!super::read().unwrap().interrupts.is_empty());
+    }
+
+    #[test]
+    fn test_read_and_parse_interrupts() {
+        let path = Path::new("fixtures/linux/interrupts/proc_interrupts");
+        let measurement = super::os::read_and_parse_interrupts(path).unwrap();
+
+        assert!(measurement.precise_time_ns > 0);
+
+        let interrupts = measurement.interrupts;
+        assert_eq!(11, interrupts.len());
+
+        let timer = interrupts.get("0").unwrap();
+        assert_eq!(vec![36, 0, 0, 0], timer.per_cpu);
+        assert_eq!(Some("IO-APIC 2-edge timer".to_owned()), timer.description);
+        assert_eq!(vec!["timer".to_owned()], timer.devices);
+
+        let acpi = interrupts.get("9").unwrap();
+        assert_eq!(
+            vec!["acpi".to_owned(), "i801_smbus".to_owned()],
+            acpi.devices
+        );
+
+        let eth0 = interrupts.get("24").unwrap();
+        assert_eq!(vec![12000, 100, 0, 50], eth0.per_cpu);
+        assert_eq!(12150, eth0.total());
+        assert_eq!(vec!["eth0-TxRx-0".to_owned()], eth0.devices);
+
+        let virtio = interrupts.get("26").unwrap();
+        assert_eq!(vec!["virtio0-config".to_owned()], virtio.devices);
+
+        let loc = interrupts.get("LOC").unwrap();
+        assert_eq!(vec![224213, 180340, 170212, 168001], loc.per_cpu);
+        assert_eq!(Some("Local timer interrupts".to_owned()), loc.description);
+        assert!(loc.devices.is_empty());
+
+        let err = interrupts.get("ERR").unwrap();
+        assert_eq!(vec![0], err.per_cpu);
+        assert_eq!(None, err.description);
+    }
+
+    #[test]
+    fn test_read_and_parse_interrupts_legacy() {
+        let path = Path::new("fixtures/linux/interrupts/proc_interrupts_legacy");
+        let interrupts = super::os::read_and_parse_interrupts(path)
+            .unwrap()
+            .interrupts;
+
+        assert_eq!(5, interrupts.len());
+
+        let rtc = interrupts.get("8").unwrap();
+        assert_eq!(vec![1, 0], rtc.per_cpu);
+        assert_eq!(vec!["rtc0".to_owned()], rtc.devices);
+
+        let usb = interrupts.get("16").unwrap();
+        assert_eq!(
+            vec!["ehci_hcd:usb1".to_owned(), "eth1".to_owned()],
+            usb.devices
+        );
+    }
+
+    #[test]
+    fn test_read_and_parse_interrupts_arm() {
+        let path = Path::new("fixtures/linux/interrupts/proc_interrupts_arm");
+        let interrupts = super::os::read_and_parse_interrupts(path)
+            .unwrap()
+            .interrupts;
+
+        assert_eq!(8, interrupts.len());
+
+        let timer = interrupts.get("11").unwrap();
+        assert_eq!(vec![145310, 142546, 140712, 140384], timer.per_cpu);
+        assert_eq!(vec!["arch_timer".to_owned()], timer.devices);
+
+        let mmc = interrupts.get("57").unwrap();
+        assert_eq!(vec!["mmc0".to_owned(), "mmc1".to_owned()], mmc.devices);
+
+        let nvme = interrupts.get("63").unwrap();
+        assert_eq!(vec!["nvme0q0".to_owned()], nvme.devices);
+
+        let rescheduling = interrupts.get("IPI0").unwrap();
+        assert_eq!(
+            Some("Rescheduling interrupts".to_owned()),
+            rescheduling.description
+        );
+        assert!(rescheduling.devices.is_empty());
+
+        assert_eq!(vec![0], interrupts.get("Err").unwrap().per_cpu);
+    }
+
+    #[test]
+    fn test_read_and_parse_interrupts_wrong_path() {
+        match super::os::read_and_parse_interrupts(Path::new("/nonsense")) {
+            Err(ProbeError::IO(_, _)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_interrupts_incomplete() {
+        let path = Path::new("fixtures/linux/interrupts/proc_interrupts_incomplete");
+        match super::os::read_and_parse_interrupts(path) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_interrupts_garbage() {
+        let path = Path::new("fixtures/linux/interrupts/proc_interrupts_garbage");
+        match super::os::read_and_parse_interrupts(path) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_full_minute() {
+        let measurement1 = helpers::measurement(60_000_000_000, vec![100, 200]);
+        let measurement2 = helpers::measurement(120_000_000_000, vec![160, 320]);
+
+        let per_minute = measurement1.calculate_per_minute(&measurement2).unwrap();
+        let eth0 = per_minute.interrupts.get("24").unwrap();
+        assert_eq!(vec![60, 120], eth0.per_cpu);
+        assert_eq!(180, eth0.total());
+        assert_eq!(vec!["eth0".to_owned()], eth0.devices);
+    }
+
+    #[test]
+    fn test_calculate_per_minute_partial_minute() {
+        let measurement1 = helpers::measurement(60_000_000_000, vec![100, 200]);
+        let measurement2 = helpers::measurement(90_000_000_000, vec![160, 320]);
+
+        let per_minute = measurement1.calculate_per_minute(&measurement2).unwrap();
+        let eth0 = per_minute.interrupts.get("24").unwrap();
+        assert_eq!(vec![120, 240], eth0.per_cpu);
+    }
+
+    #[test]
+    fn test_calculate_per_minute_wrong_times() {
+        let measurement1 = helpers::measurement(90_000_000_000, vec![100, 200]);
+        let measurement2 = helpers::measurement(60_000_000_000, vec![160, 320]);
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::InvalidInput(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_values_lower() {
+        let measurement1 = helpers::measurement(60_000_000_000, vec![100, 200]);
+        let measurement2 = helpers::measurement(120_000_000_000, vec![160, 100]);
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_different_cpus() {
+        let measurement1 = helpers::measurement(60_000_000_000, vec![100, 200]);
+        let measurement2 = helpers::measurement(120_000_000_000, vec![160, 320, 10]);
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_different_interrupts() {
+        let measurement1 = helpers::measurement(60_000_000_000, vec![100, 200]);
+        let measurement2 = InterruptsMeasurement {
+            precise_time_ns: 120_000_000_000,
+            interrupts: Interrupts::new(),
+        };
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    mod helpers {
+        use super::{Interrupt, Interrupts, InterruptsMeasurement};
+
+        pub fn measurement(precise_time_ns: u64, per_cpu: Vec<u64>) -> InterruptsMeasurement {
+            let mut interrupts = Interrupts::new();
+            interrupts.insert(
+                "24".to_owned(),
+                Interrupt {
+                    per_cpu,
+                    description: Some("PCI-MSI 524288-edge eth0".to_owned()),
+                    devices: vec!["eth0".to_owned()],
+                },
+            );
+            InterruptsMeasurement {
+                precise_time_ns,
+                interrupts,
+            }
+        }
+    }
+}
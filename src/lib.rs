@@ -1,19 +1,30 @@
 extern crate libc;
 
+pub mod block_devices;
+pub mod block_io;
 pub mod cpu;
 pub mod disk_stats;
 pub mod disk_usage;
 mod error;
+pub mod interrupts;
 pub mod load;
 pub mod memory;
+pub mod mountpoint_stats;
+pub mod mounts;
 pub mod network;
 pub mod process_memory;
+pub mod sensors;
+pub mod softirqs;
+pub mod swap;
+pub mod vmstat;
 
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fs;
 use std::io;
 use std::io::BufRead;
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::result;
 use std::time::SystemTime;
 
@@ -34,7 +45,7 @@ fn file_to_string(path: &Path) -> Result<String> {
 fn file_to_buf_reader(path: &Path) -> Result<io::BufReader<fs::File>> {
     fs::File::open(path)
         .map_err(|e| ProbeError::IO(e, path_to_string(path)))
-        .and_then(|f| Ok(io::BufReader::new(f)))
+        .map(io::BufReader::new)
 }
 
 #[inline]
@@ -61,19 +72,147 @@ fn time_adjusted(
     second_value: u64,
     time_difference_ns: u64,
 ) -> Result<u64> {
-    if first_value < second_value {
-        Err(ProbeError::UnexpectedContent(format!(
+    let difference = counter_difference(field_name, first_value, second_value)?;
+    Ok((difference as f64 / time_difference_ns as f64 * 60_000_000_000.0) as u64)
+}
+
+/// Increase of a counter between two measurements. A counter that went down, such as after a
+/// reset, is an error.
+#[inline]
+fn counter_difference(field_name: &str, first_value: u64, second_value: u64) -> Result<u64> {
+    first_value.checked_sub(second_value).ok_or_else(|| {
+        ProbeError::UnexpectedContent(format!(
             "First value {} was lower than second value {} for '{}'",
             first_value, second_value, field_name
-        )))
-    } else {
-        Ok(
-            ((first_value - second_value) as f64 / time_difference_ns as f64 * 60_000_000_000.0)
-                as u64,
-        )
+        ))
+    })
+}
+
+/// Kind of a field in a measurement, which determines how it is reported per minute
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum FieldKind {
+    /// A value which only increases, such as the number of reads. Reported as the increase per
+    /// minute.
+    Counter,
+    /// A current value which can go up and down, such as the number of I/Os in progress.
+    /// Reported as the value of the latest measurement.
+    Gauge,
+}
+
+/// Calculate the per-minute value of a field based on its kind. The first value is from the
+/// latest measurement.
+#[inline]
+fn per_minute_value(
+    field_name: &str,
+    kind: FieldKind,
+    first_value: u64,
+    second_value: u64,
+    time_difference_ns: u64,
+) -> Result<u64> {
+    match kind {
+        FieldKind::Counter => {
+            time_adjusted(field_name, first_value, second_value, time_difference_ns)
+        }
+        FieldKind::Gauge => Ok(first_value),
+    }
+}
+
+/// Calculate the per-minute value of a field that is not reported by every kernel version. A value
+/// that disappeared in the next measurement is an error.
+#[inline]
+fn per_minute_value_option(
+    field_name: &str,
+    kind: FieldKind,
+    first_value: Option<u64>,
+    second_value: Option<u64>,
+    time_difference_ns: u64,
+) -> Result<Option<u64>> {
+    match (first_value, second_value) {
+        (Some(first_value), Some(second_value)) => Ok(Some(per_minute_value(
+            field_name,
+            kind,
+            first_value,
+            second_value,
+            time_difference_ns,
+        )?)),
+        (None, Some(_)) => Err(ProbeError::UnexpectedContent(format!(
+            "{} is not present in the next measurement",
+            field_name
+        ))),
+        (_, None) => Ok(None),
     }
 }
 
+/// Calculate the per-minute values of a field that is reported for every CPU.
+#[inline]
+fn per_minute_value_per_cpu(
+    field_name: &str,
+    kind: FieldKind,
+    first_values: &[u64],
+    second_values: &[u64],
+    time_difference_ns: u64,
+) -> Result<Vec<u64>> {
+    if first_values.len() != second_values.len() {
+        return Err(ProbeError::UnexpectedContent(format!(
+            "Number of CPUs changed from {} to {} for '{}'",
+            second_values.len(),
+            first_values.len(),
+            field_name
+        )));
+    }
+
+    first_values
+        .iter()
+        .zip(second_values.iter())
+        .map(|(first_value, second_value)| {
+            per_minute_value(
+                field_name,
+                kind,
+                *first_value,
+                *second_value,
+                time_difference_ns,
+            )
+        })
+        .collect()
+}
+
+/// Calculate the per-minute counts of every named counter that is reported per CPU, such as an
+/// interrupt or softirq. The first values are from the latest measurement, every result is built
+/// from its latest value and its per-minute counts. A name that disappeared in the next
+/// measurement is an error.
+fn per_minute_counters_per_cpu<T>(
+    first_values: &HashMap<String, T>,
+    second_values: &HashMap<String, T>,
+    time_difference_ns: u64,
+    per_cpu: impl Fn(&T) -> &[u64],
+    build: impl Fn(&T, Vec<u64>) -> T,
+) -> Result<HashMap<String, T>> {
+    let mut out = HashMap::new();
+
+    for (name, second_value) in second_values.iter() {
+        let first_value = match first_values.get(name) {
+            Some(value) => value,
+            None => {
+                return Err(ProbeError::UnexpectedContent(format!(
+                    "{} is not present in the next measurement",
+                    name
+                )))
+            }
+        };
+
+        let counts = per_minute_value_per_cpu(
+            name,
+            FieldKind::Counter,
+            per_cpu(first_value),
+            per_cpu(second_value),
+            time_difference_ns,
+        )?;
+        out.insert(name.to_owned(), build(first_value, counts));
+    }
+
+    Ok(out)
+}
+
 #[inline]
 fn parse_u64(segment: &str) -> Result<u64> {
     segment
@@ -81,11 +220,71 @@ fn parse_u64(segment: &str) -> Result<u64> {
         .map_err(|_| ProbeError::UnexpectedContent(format!("Could not parse '{}' as u64", segment)))
 }
 
+#[inline]
+fn parse_i64(segment: &str) -> Result<i64> {
+    segment
+        .parse()
+        .map_err(|_| ProbeError::UnexpectedContent(format!("Could not parse '{}' as i64", segment)))
+}
+
+/// Decode the octal escapes the kernel uses for whitespace and backslashes in paths, such as
+/// `\040` for a space in `/proc/swaps` and `/proc/self/mountinfo`.
+#[inline]
+fn unescape_octal(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 4 <= bytes.len() {
+            let escape = &bytes[i + 1..i + 4];
+            if escape.iter().all(|digit| (b'0'..=b'7').contains(digit)) {
+                let value = escape
+                    .iter()
+                    .fold(0u32, |value, digit| value * 8 + u32::from(digit - b'0'));
+                if let Ok(byte) = u8::try_from(value) {
+                    out.push(byte);
+                    i += 4;
+                    continue;
+                }
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).to_string()
+}
+
 #[inline]
 fn dir_exists(path: &Path) -> bool {
     path.exists() && path.is_dir()
 }
 
+/// List the entries in a directory named after a prefix and a number, such as `cpu0` or
+/// `hwmon1`, ordered by that number.
+#[inline]
+fn numbered_dir_entries(path: &Path, prefix: &str) -> Result<Vec<(u32, PathBuf)>> {
+    let entries = fs::read_dir(path).map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
+
+    let mut out = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
+        let file_name = entry.file_name();
+        let number = file_name
+            .to_str()
+            .and_then(|name| name.strip_prefix(prefix))
+            .and_then(|number| number.parse().ok());
+
+        if let Some(number) = number {
+            out.push((number, entry.path()));
+        }
+    }
+    out.sort();
+
+    Ok(out)
+}
+
 #[inline]
 fn read_file_value_as_u64(path: &Path) -> Result<u64> {
     let mut reader = file_to_buf_reader(path)?;
@@ -93,15 +292,49 @@ fn read_file_value_as_u64(path: &Path) -> Result<u64> {
     reader
         .read_line(&mut line)
         .map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
-    parse_u64(&line.trim())
+    parse_u64(line.trim())
+}
+
+/// Read a file with a key and a numeric value on every line, such as `memory.events`.
+#[inline]
+fn read_key_value_file(path: &Path) -> Result<HashMap<String, u64>> {
+    let reader = file_to_buf_reader(path)?;
+
+    let mut out = HashMap::new();
+    for line_result in reader.lines() {
+        let line = line_result.map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
+        let segments: Vec<&str> = line.split_whitespace().collect();
+        match segments.len() {
+            0 => continue,
+            2 => {
+                out.insert(segments[0].to_owned(), parse_u64(segments[1])?);
+            }
+            _ => {
+                return Err(ProbeError::UnexpectedContent(format!(
+                    "Incorrect number of segments in '{}'",
+                    line
+                )))
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/// Remove a value that has to be present from the values read with `read_key_value_file`.
+#[inline]
+fn take_required_value(values: &mut HashMap<String, u64>, key: &str) -> Result<u64> {
+    values
+        .remove(key)
+        .ok_or_else(|| ProbeError::UnexpectedContent(format!("{} is not present", key)))
 }
 
 #[inline]
 fn precise_time_ns() -> u64 {
-    return SystemTime::now()
+    SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap()
-        .as_nanos() as u64;
+        .as_nanos() as u64
 }
 
 fn bytes_to_kilo_bytes(bytes: u64) -> u64 {
@@ -111,6 +344,8 @@ fn bytes_to_kilo_bytes(bytes: u64) -> u64 {
 #[cfg(test)]
 mod tests {
     use crate::error::ProbeError;
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
 
     #[test]
     fn test_calculate_time_difference() {
@@ -142,9 +377,256 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_counter_difference() {
+        assert_eq!(
+            1200,
+            super::counter_difference("field", 2400, 1200).unwrap()
+        );
+        assert_eq!(0, super::counter_difference("field", 1200, 1200).unwrap());
+        match super::counter_difference("field", 1200, 2400) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_per_minute_value() {
+        use super::FieldKind;
+
+        assert_eq!(
+            2400,
+            super::per_minute_value("field", FieldKind::Counter, 2400, 1200, 30_000_000_000)
+                .unwrap()
+        );
+        assert_eq!(
+            2400,
+            super::per_minute_value("field", FieldKind::Gauge, 2400, 1200, 30_000_000_000).unwrap()
+        );
+        // A gauge can go down
+        assert_eq!(
+            1200,
+            super::per_minute_value("field", FieldKind::Gauge, 1200, 2400, 30_000_000_000).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_per_minute_value_counter_first_higher_than_lower() {
+        match super::per_minute_value("field", super::FieldKind::Counter, 1200, 2400, 1) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_per_minute_value_option() {
+        assert_eq!(
+            Some(1200),
+            super::per_minute_value_option(
+                "field",
+                super::FieldKind::Counter,
+                Some(2400),
+                Some(1200),
+                60_000_000_000
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            None,
+            super::per_minute_value_option(
+                "field",
+                super::FieldKind::Counter,
+                Some(2400),
+                None,
+                60_000_000_000
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            None,
+            super::per_minute_value_option(
+                "field",
+                super::FieldKind::Counter,
+                None,
+                None,
+                60_000_000_000
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_per_minute_value_option_gauge() {
+        assert_eq!(
+            Some(1200),
+            super::per_minute_value_option(
+                "field",
+                super::FieldKind::Gauge,
+                Some(1200),
+                Some(2400),
+                60_000_000_000
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_per_minute_value_option_missing_in_next() {
+        match super::per_minute_value_option(
+            "field",
+            super::FieldKind::Counter,
+            None,
+            Some(1200),
+            60_000_000_000,
+        ) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_per_minute_value_per_cpu() {
+        assert_eq!(
+            vec![1200, 0, 600],
+            super::per_minute_value_per_cpu(
+                "field",
+                super::FieldKind::Counter,
+                &[2400, 100, 900],
+                &[1200, 100, 300],
+                60_000_000_000
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_per_minute_counters_per_cpu() {
+        let mut first = HashMap::new();
+        first.insert("NET_RX".to_owned(), vec![2400, 100]);
+        let mut second = HashMap::new();
+        second.insert("NET_RX".to_owned(), vec![1200, 100]);
+
+        let per_minute = super::per_minute_counters_per_cpu(
+            &first,
+            &second,
+            60_000_000_000,
+            |counts| counts,
+            |_, counts| counts,
+        )
+        .unwrap();
+        assert_eq!(Some(&vec![1200, 0]), per_minute.get("NET_RX"));
+    }
+
+    #[test]
+    fn test_per_minute_counters_per_cpu_missing_in_next() {
+        let first: HashMap<String, Vec<u64>> = HashMap::new();
+        let mut second = HashMap::new();
+        second.insert("NET_RX".to_owned(), vec![1200, 100]);
+
+        match super::per_minute_counters_per_cpu(
+            &first,
+            &second,
+            60_000_000_000,
+            |counts| counts,
+            |_, counts| counts,
+        ) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_per_minute_value_per_cpu_different_cpu_count() {
+        match super::per_minute_value_per_cpu(
+            "field",
+            super::FieldKind::Counter,
+            &[2400, 100],
+            &[1200],
+            60_000_000_000,
+        ) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
     #[test]
     fn test_parse_u64() {
         assert_eq!(100, super::parse_u64("100").unwrap());
         assert!(super::parse_u64("something").is_err());
     }
+
+    #[test]
+    fn test_parse_i64() {
+        assert_eq!(-100, super::parse_i64("-100").unwrap());
+        assert!(super::parse_i64("something").is_err());
+    }
+
+    #[test]
+    fn test_unescape_octal() {
+        assert_eq!("/mnt/my disk", super::unescape_octal("/mnt/my\\040disk"));
+        assert_eq!("tab\there", super::unescape_octal("tab\\011here"));
+        assert_eq!("back\\slash", super::unescape_octal("back\\134slash"));
+        assert_eq!("/plain", super::unescape_octal("/plain"));
+        assert_eq!("trailing\\04", super::unescape_octal("trailing\\04"));
+        assert_eq!("not\\8octal", super::unescape_octal("not\\8octal"));
+    }
+
+    #[test]
+    fn test_numbered_dir_entries() {
+        let entries = super::numbered_dir_entries(
+            Path::new("fixtures/linux/sys/class/thermal"),
+            "thermal_zone",
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                (
+                    0,
+                    PathBuf::from("fixtures/linux/sys/class/thermal/thermal_zone0")
+                ),
+                (
+                    1,
+                    PathBuf::from("fixtures/linux/sys/class/thermal/thermal_zone1")
+                ),
+            ],
+            entries
+        );
+    }
+
+    #[test]
+    fn test_read_key_value_file() {
+        let values = super::read_key_value_file(Path::new(
+            "fixtures/linux/sys/fs/cgroup_v1/memory/memory.oom_control",
+        ))
+        .unwrap();
+        assert_eq!(3, values.len());
+        assert_eq!(Some(&1), values.get("under_oom"));
+        assert_eq!(Some(&4), values.get("oom_kill"));
+    }
+
+    #[test]
+    fn test_take_required_value() {
+        let mut values = HashMap::new();
+        values.insert("oom_kill".to_owned(), 4);
+
+        assert_eq!(
+            4,
+            super::take_required_value(&mut values, "oom_kill").unwrap()
+        );
+        assert!(values.is_empty());
+        match super::take_required_value(&mut values, "oom_kill") {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_key_value_file_garbage() {
+        match super::read_key_value_file(Path::new(
+            "fixtures/linux/sys/fs/cgroup_v2/memory_garbage/memory.events",
+        )) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
 }
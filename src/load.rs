@@ -24,7 +24,7 @@ mod os {
 
     #[inline]
     pub fn read() -> Result<LoadAverage> {
-        read_and_parse_load_average(&Path::new("/proc/loadavg"))
+        read_and_parse_load_average(Path::new("/proc/loadavg"))
     }
 
     #[inline]
@@ -68,7 +68,7 @@ mod tests {
     #[test]
     fn test_read_and_parse_load_average() {
         let path = Path::new("fixtures/linux/load/proc_loadavg");
-        let load_average = super::os::read_and_parse_load_average(&path).unwrap();
+        let load_average = super::os::read_and_parse_load_average(path).unwrap();
 
         let expected = LoadAverage {
             one: 0.01,
@@ -82,7 +82,7 @@ mod tests {
     #[test]
     fn test_read_and_parse_load_average_wrong_path() {
         let path = Path::new("/nonsense");
-        match super::os::read_and_parse_load_average(&path) {
+        match super::os::read_and_parse_load_average(path) {
             Err(ProbeError::IO(_, _)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -91,7 +91,7 @@ mod tests {
     #[test]
     fn test_read_and_parse_load_average_incomplete() {
         let path = Path::new("fixtures/linux/load/proc_loadavg_incomplete");
-        match super::os::read_and_parse_load_average(&path) {
+        match super::os::read_and_parse_load_average(path) {
             Err(ProbeError::UnexpectedContent(_)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -100,7 +100,7 @@ mod tests {
     #[test]
     fn test_read_and_parse_load_average_garbage() {
         let path = Path::new("fixtures/linux/load/proc_loadavg_garbage");
-        match super::os::read_and_parse_load_average(&path) {
+        match super::os::read_and_parse_load_average(path) {
             Err(ProbeError::UnexpectedContent(_)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
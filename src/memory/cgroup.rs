@@ -1,7 +1,196 @@
+use std::collections::HashMap;
 use std::path::Path;
 
 use super::Memory;
-use crate::{dir_exists, ProbeError, Result};
+use crate::{
+    calculate_time_difference, dir_exists, per_minute_value_option, FieldKind, ProbeError, Result,
+};
+
+/// Breakdown of the memory usage of the container from `memory.stat`. Sizes are in bytes,
+/// the `pg*` and `workingset_*` fields are event counts.
+///
+/// On cgroups v1 the hierarchical `total_*` fields are used, so the values include the usage
+/// of child cgroups like they do on cgroups v2.
+#[derive(Debug, Default, PartialEq)]
+pub struct CgroupMemoryStat {
+    /// Anonymous memory, `rss` on cgroups v1
+    pub anon: Option<u64>,
+    /// Page cache, `cache` on cgroups v1
+    pub file: Option<u64>,
+    pub kernel_stack: Option<u64>,
+    pub slab: Option<u64>,
+    pub slab_reclaimable: Option<u64>,
+    pub slab_unreclaimable: Option<u64>,
+    pub sock: Option<u64>,
+    pub shmem: Option<u64>,
+    pub file_mapped: Option<u64>,
+    pub file_dirty: Option<u64>,
+    pub file_writeback: Option<u64>,
+    /// Anonymous transparent huge pages, `rss_huge` on cgroups v1
+    pub anon_thp: Option<u64>,
+    pub active_anon: Option<u64>,
+    pub inactive_anon: Option<u64>,
+    pub active_file: Option<u64>,
+    pub inactive_file: Option<u64>,
+    pub unevictable: Option<u64>,
+    pub pgfault: Option<u64>,
+    pub pgmajfault: Option<u64>,
+    pub workingset_refault_anon: Option<u64>,
+    pub workingset_refault_file: Option<u64>,
+    pub workingset_activate_anon: Option<u64>,
+    pub workingset_activate_file: Option<u64>,
+    pub workingset_restore_anon: Option<u64>,
+    pub workingset_restore_file: Option<u64>,
+    /// Fields without a dedicated field in this struct, keyed by their name in `memory.stat`
+    pub other: HashMap<String, u64>,
+}
+
+/// Memory thresholds of the container below its hard limit, in KB. A threshold that is not
+/// configured is `None`, which the kernel reports as `max` for `memory.high` and as 0 for
+/// `memory.low` and `memory.min`.
+#[derive(Debug, Default, PartialEq)]
+pub struct CgroupMemoryThresholds {
+    /// Usage above which the container is throttled and reclaimed, `memory.high`
+    pub high: Option<u64>,
+    /// Usage below which memory is only reclaimed when there is no other option, `memory.low`
+    pub low: Option<u64>,
+    /// Usage below which memory is never reclaimed, `memory.min`
+    pub min: Option<u64>,
+    /// Usage above which memory is reclaimed first when the system is under memory pressure,
+    /// `memory.soft_limit_in_bytes` on cgroups v1
+    pub soft_limit: Option<u64>,
+}
+
+/// Huge page usage of the container for a single page size, in KB.
+#[derive(Debug, PartialEq)]
+pub struct CgroupHugetlb {
+    /// Size of a page in KB
+    pub page_size: u64,
+    pub usage: u64,
+    /// `None` when no limit is set
+    pub limit: Option<u64>,
+}
+
+/// Number of times the container hit one of its memory boundaries.
+#[derive(Debug, Default, PartialEq)]
+pub struct CgroupMemoryEvents {
+    /// Reclaimed while below its `memory.low` protection
+    pub low: Option<u64>,
+    /// Throttled for exceeding `memory.high`
+    pub high: Option<u64>,
+    /// Usage was about to exceed `memory.max`
+    pub max: Option<u64>,
+    /// Reached the limit and failed to allocate memory
+    pub oom: Option<u64>,
+    /// Processes killed by the OOM killer
+    pub oom_kill: Option<u64>,
+    /// Usage reached the limit, `memory.failcnt` on cgroups v1
+    pub failcnt: Option<u64>,
+}
+
+impl CgroupMemoryEvents {
+    fn calculate_per_minute(
+        &self,
+        next_events: &CgroupMemoryEvents,
+        time_difference: u64,
+    ) -> Result<CgroupMemoryEvents> {
+        Ok(CgroupMemoryEvents {
+            low: per_minute_value_option(
+                "low",
+                FieldKind::Counter,
+                next_events.low,
+                self.low,
+                time_difference,
+            )?,
+            high: per_minute_value_option(
+                "high",
+                FieldKind::Counter,
+                next_events.high,
+                self.high,
+                time_difference,
+            )?,
+            max: per_minute_value_option(
+                "max",
+                FieldKind::Counter,
+                next_events.max,
+                self.max,
+                time_difference,
+            )?,
+            oom: per_minute_value_option(
+                "oom",
+                FieldKind::Counter,
+                next_events.oom,
+                self.oom,
+                time_difference,
+            )?,
+            oom_kill: per_minute_value_option(
+                "oom_kill",
+                FieldKind::Counter,
+                next_events.oom_kill,
+                self.oom_kill,
+                time_difference,
+            )?,
+            failcnt: per_minute_value_option(
+                "failcnt",
+                FieldKind::Counter,
+                next_events.failcnt,
+                self.failcnt,
+                time_difference,
+            )?,
+        })
+    }
+}
+
+/// Measurement of the memory events of the container at a certain time.
+#[derive(Debug, PartialEq)]
+pub struct CgroupMemoryEventsMeasurement {
+    pub precise_time_ns: u64,
+    /// Events of the container and all cgroups below it
+    pub events: CgroupMemoryEvents,
+    /// Events of the container's own cgroup, only available on cgroups v2 with Linux 5.2 or newer
+    pub local_events: Option<CgroupMemoryEvents>,
+    /// Whether the container is currently out of memory, only available on cgroups v1
+    pub under_oom: Option<bool>,
+}
+
+impl CgroupMemoryEventsMeasurement {
+    /// Calculate the memory events per minute based on this measurement and a measurement in the
+    /// future. It is advisable to make the next measurement roughly a minute from this one for the
+    /// most reliable result.
+    pub fn calculate_per_minute(
+        &self,
+        next_measurement: &CgroupMemoryEventsMeasurement,
+    ) -> Result<CgroupMemoryEventsPerMinute> {
+        let time_difference =
+            calculate_time_difference(self.precise_time_ns, next_measurement.precise_time_ns)?;
+
+        let local_events = match (&self.local_events, &next_measurement.local_events) {
+            (Some(local_events), Some(next_local_events)) => {
+                Some(local_events.calculate_per_minute(next_local_events, time_difference)?)
+            }
+            (Some(_), None) => {
+                return Err(ProbeError::UnexpectedContent(
+                    "local_events is not present in the next measurement".to_owned(),
+                ))
+            }
+            (None, _) => None,
+        };
+
+        Ok(CgroupMemoryEventsPerMinute {
+            events: self
+                .events
+                .calculate_per_minute(&next_measurement.events, time_difference)?,
+            local_events,
+        })
+    }
+}
+
+/// Memory events of the container for a certain minute, calculated based on two measurements.
+#[derive(Debug, PartialEq)]
+pub struct CgroupMemoryEventsPerMinute {
+    pub events: CgroupMemoryEvents,
+    pub local_events: Option<CgroupMemoryEvents>,
+}
 
 /// Read the current memory status of the container.
 #[cfg(target_os = "linux")]
@@ -9,16 +198,88 @@ pub fn read() -> Result<Memory> {
     use super::cgroup_v1::read_and_parse_v1_sys_memory;
     use super::cgroup_v2::read_and_parse_v2_sys_memory;
 
+    match find_memory_cgroup()? {
+        MemoryCgroup::V1(dir) => read_and_parse_v1_sys_memory(dir),
+        MemoryCgroup::V2(dir) => read_and_parse_v2_sys_memory(dir),
+    }
+}
+
+/// Read the current `memory.stat` breakdown of the container.
+#[cfg(target_os = "linux")]
+pub fn read_stat() -> Result<CgroupMemoryStat> {
+    use super::cgroup_v1::read_and_parse_v1_sys_memory_stat;
+    use super::cgroup_v2::read_and_parse_v2_sys_memory_stat;
+
+    match find_memory_cgroup()? {
+        MemoryCgroup::V1(dir) => read_and_parse_v1_sys_memory_stat(dir),
+        MemoryCgroup::V2(dir) => read_and_parse_v2_sys_memory_stat(dir),
+    }
+}
+
+/// Read the memory thresholds of the container, such as the throttling threshold.
+#[cfg(target_os = "linux")]
+pub fn read_thresholds() -> Result<CgroupMemoryThresholds> {
+    use super::cgroup_v1::read_and_parse_v1_sys_memory_thresholds;
+    use super::cgroup_v2::read_and_parse_v2_sys_memory_thresholds;
+
+    match find_memory_cgroup()? {
+        MemoryCgroup::V1(dir) => read_and_parse_v1_sys_memory_thresholds(dir),
+        MemoryCgroup::V2(dir) => read_and_parse_v2_sys_memory_thresholds(dir),
+    }
+}
+
+/// Read the current huge page usage and limits of the container for every page size. Returns
+/// no page sizes when the `hugetlb` controller is not available on cgroups v1.
+#[cfg(target_os = "linux")]
+pub fn read_hugetlb() -> Result<Vec<CgroupHugetlb>> {
+    use super::cgroup_v1::read_and_parse_v1_sys_hugetlb;
+    use super::cgroup_v2::read_and_parse_v2_sys_hugetlb;
+
+    match find_memory_cgroup()? {
+        MemoryCgroup::V1(_) => {
+            // On cgroups v1 huge pages are a separate controller
+            let v1_sys_fs_dir = Path::new("/sys/fs/cgroup/hugetlb/");
+            if dir_exists(v1_sys_fs_dir) {
+                read_and_parse_v1_sys_hugetlb(v1_sys_fs_dir)
+            } else {
+                Ok(Vec::new())
+            }
+        }
+        MemoryCgroup::V2(dir) => read_and_parse_v2_sys_hugetlb(dir),
+    }
+}
+
+/// Read the current memory events of the container, such as throttling and OOM kills.
+#[cfg(target_os = "linux")]
+pub fn read_events() -> Result<CgroupMemoryEventsMeasurement> {
+    use super::cgroup_v1::read_and_parse_v1_sys_memory_events;
+    use super::cgroup_v2::read_and_parse_v2_sys_memory_events;
+
+    match find_memory_cgroup()? {
+        MemoryCgroup::V1(dir) => read_and_parse_v1_sys_memory_events(dir),
+        MemoryCgroup::V2(dir) => read_and_parse_v2_sys_memory_events(dir),
+    }
+}
+
+/// Directory with the memory files of the container for the cgroups version in use
+#[cfg(target_os = "linux")]
+enum MemoryCgroup {
+    V1(&'static Path),
+    V2(&'static Path),
+}
+
+#[cfg(target_os = "linux")]
+fn find_memory_cgroup() -> Result<MemoryCgroup> {
     let v2_sys_fs_dir = Path::new("/sys/fs/cgroup");
     let v2_sys_fs_file = v2_sys_fs_dir.join("memory.current");
 
     if v2_sys_fs_file.exists() {
-        return read_and_parse_v2_sys_memory(&v2_sys_fs_dir);
+        return Ok(MemoryCgroup::V2(v2_sys_fs_dir));
     }
 
     let v1_sys_fs_dir = Path::new("/sys/fs/cgroup/memory/");
     if dir_exists(v1_sys_fs_dir) {
-        return read_and_parse_v1_sys_memory(&v1_sys_fs_dir);
+        return Ok(MemoryCgroup::V1(v1_sys_fs_dir));
     }
 
     let message = format!(
@@ -31,8 +292,130 @@ pub fn read() -> Result<Memory> {
 #[cfg(test)]
 #[cfg(target_os = "linux")]
 mod tests {
+    use super::{CgroupMemoryEvents, CgroupMemoryEventsMeasurement};
+    use crate::ProbeError;
+
     #[test]
     fn test_read_from_container() {
         assert!(super::read().is_ok());
     }
+
+    #[test]
+    fn test_read_stat_from_container() {
+        assert!(super::read_stat().is_ok());
+    }
+
+    #[test]
+    fn test_read_thresholds_from_container() {
+        assert!(super::read_thresholds().is_ok());
+    }
+
+    #[test]
+    fn test_read_hugetlb_from_container() {
+        assert!(super::read_hugetlb().is_ok());
+    }
+
+    #[test]
+    fn test_read_events_from_container() {
+        assert!(super::read_events().is_ok());
+    }
+
+    #[test]
+    fn test_calculate_per_minute_full_minute() {
+        let measurement1 = helpers::measurement(60_000_000_000, 10, Some(2));
+        let measurement2 = helpers::measurement(120_000_000_000, 40, Some(5));
+
+        let per_minute = measurement1.calculate_per_minute(&measurement2).unwrap();
+        let expected = CgroupMemoryEvents {
+            high: Some(30),
+            oom_kill: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(expected, per_minute.events);
+        assert_eq!(Some(expected), per_minute.local_events);
+    }
+
+    #[test]
+    fn test_calculate_per_minute_partial_minute() {
+        let measurement1 = helpers::measurement(60_000_000_000, 10, None);
+        let measurement2 = helpers::measurement(90_000_000_000, 40, None);
+
+        let per_minute = measurement1.calculate_per_minute(&measurement2).unwrap();
+        assert_eq!(Some(60), per_minute.events.high);
+        assert_eq!(None, per_minute.events.oom_kill);
+    }
+
+    #[test]
+    fn test_calculate_per_minute_wrong_times() {
+        let measurement1 = helpers::measurement(90_000_000_000, 10, Some(2));
+        let measurement2 = helpers::measurement(60_000_000_000, 40, Some(5));
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::InvalidInput(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_values_lower() {
+        let measurement1 = helpers::measurement(60_000_000_000, 40, Some(2));
+        let measurement2 = helpers::measurement(120_000_000_000, 10, Some(5));
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_missing_events() {
+        let measurement1 = helpers::measurement(60_000_000_000, 10, Some(2));
+        let measurement2 = helpers::measurement(120_000_000_000, 40, None);
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_missing_local_events() {
+        let measurement1 = helpers::measurement(60_000_000_000, 10, Some(2));
+        let measurement2 = CgroupMemoryEventsMeasurement {
+            local_events: None,
+            ..helpers::measurement(120_000_000_000, 40, Some(5))
+        };
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    mod helpers {
+        use super::{CgroupMemoryEvents, CgroupMemoryEventsMeasurement};
+
+        pub fn measurement(
+            precise_time_ns: u64,
+            high: u64,
+            oom_kill: Option<u64>,
+        ) -> CgroupMemoryEventsMeasurement {
+            let events = CgroupMemoryEvents {
+                high: Some(high),
+                oom_kill,
+                ..Default::default()
+            };
+            let local_events = CgroupMemoryEvents {
+                high: Some(high),
+                oom_kill,
+                ..Default::default()
+            };
+            CgroupMemoryEventsMeasurement {
+                precise_time_ns,
+                events,
+                local_events: Some(local_events),
+                under_oom: None,
+            }
+        }
+    }
 }
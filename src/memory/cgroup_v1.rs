@@ -1,9 +1,16 @@
 use std::io::BufRead;
 use std::path::Path;
 
-use super::Memory;
+use super::cgroup::{
+    CgroupHugetlb, CgroupMemoryEvents, CgroupMemoryEventsMeasurement, CgroupMemoryStat,
+    CgroupMemoryThresholds,
+};
+use super::{clamped_sub, hugetlb_page_sizes, Memory};
 use crate::{bytes_to_kilo_bytes, file_to_buf_reader, parse_u64, read_file_value_as_u64};
-use crate::{path_to_string, ProbeError, Result};
+use crate::{path_to_string, precise_time_ns, read_key_value_file, ProbeError, Result};
+
+// Number reported by cgroups v1 when no limit is set
+const UNLIMITED: u64 = 9223372036854771712;
 
 #[cfg(target_os = "linux")]
 pub fn read_and_parse_v1_sys_memory(path: &Path) -> Result<Memory> {
@@ -17,35 +24,36 @@ pub fn read_and_parse_v1_sys_memory(path: &Path) -> Result<Memory> {
         swap_total: None,
         swap_free: None,
         swap_used: None,
+        rss: None,
+        working_set: None,
+        inconsistent: false,
     };
 
     let limit = read_file_value_as_u64(&path.join("memory.limit_in_bytes"))?;
-    // Ignore number reported by cgroups v1 when no limit is set
-    if limit < 9223372036854771712u64 {
+    if limit < UNLIMITED {
         memory.total = Some(bytes_to_kilo_bytes(limit));
     }
 
-    let used_memory =
-        bytes_to_kilo_bytes(read_file_value_as_u64(&path.join("memory.usage_in_bytes"))?);
+    // The files are not read at the same moment, so values calculated from multiple files are
+    // clamped at zero when the usage changed in between.
+    let mut inconsistent = false;
 
-    let reader = file_to_buf_reader(&path.join("memory.stat"))?;
-    for line_result in reader.lines() {
-        let line = line_result.map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
-        let segments: Vec<&str> = line.split_whitespace().collect();
-        if let Ok(value) = parse_u64(&segments[1]) {
-            match segments[0] {
-                "shmem" => {
-                    memory.shmem = Some(bytes_to_kilo_bytes(value));
-                }
-                "cache" => {
-                    memory.cached = Some(bytes_to_kilo_bytes(value));
-                }
-                _ => (),
-            };
-        }
-    }
-    memory.used = used_memory - memory.cached.unwrap_or(0);
-    memory.free = memory.total.map(|total| total - memory.used);
+    let usage = read_file_value_as_u64(&path.join("memory.usage_in_bytes"))?;
+    let used_memory = bytes_to_kilo_bytes(usage);
+
+    // The usage includes the child cgroups, so use the hierarchical `total_` values of the stats
+    let stat = read_and_parse_v1_sys_memory_stat(path)?;
+    memory.shmem = stat.shmem.map(bytes_to_kilo_bytes);
+    memory.cached = stat.file.map(bytes_to_kilo_bytes);
+    memory.rss = stat.anon.map(bytes_to_kilo_bytes);
+    memory.working_set = stat.inactive_file.map(|inactive_file| {
+        bytes_to_kilo_bytes(clamped_sub(usage, inactive_file, &mut inconsistent))
+    });
+
+    memory.used = clamped_sub(used_memory, memory.cached.unwrap_or(0), &mut inconsistent);
+    memory.free = memory
+        .total
+        .map(|total| clamped_sub(total, memory.used, &mut inconsistent));
 
     memory.swap_total = match read_file_value_as_u64(&path.join("memory.memsw.limit_in_bytes")) {
         Ok(value) => memory
@@ -54,20 +62,126 @@ pub fn read_and_parse_v1_sys_memory(path: &Path) -> Result<Memory> {
         Err(_) => None,
     };
     memory.swap_used = match read_file_value_as_u64(&path.join("memory.memsw.usage_in_bytes")) {
-        Ok(value) => Some(bytes_to_kilo_bytes(value).saturating_sub(used_memory)),
+        Ok(value) => Some(clamped_sub(
+            bytes_to_kilo_bytes(value),
+            used_memory,
+            &mut inconsistent,
+        )),
         Err(_) => None,
     };
     memory.swap_free = memory
         .swap_total
         .zip(memory.swap_used)
-        .map(|(total, used)| total.saturating_sub(used));
+        .map(|(total, used)| clamped_sub(total, used, &mut inconsistent));
+
+    memory.inconsistent = inconsistent;
 
     Ok(memory)
 }
 
+#[cfg(target_os = "linux")]
+pub fn read_and_parse_v1_sys_memory_stat(path: &Path) -> Result<CgroupMemoryStat> {
+    let mut stat = CgroupMemoryStat::default();
+
+    let stat_path = path.join("memory.stat");
+    let reader = file_to_buf_reader(&stat_path)?;
+    for line_result in reader.lines() {
+        let line = line_result.map_err(|e| ProbeError::IO(e, path_to_string(&stat_path)))?;
+        let segments: Vec<&str> = line.split_whitespace().collect();
+        if segments.len() != 2 {
+            return Err(ProbeError::UnexpectedContent(format!(
+                "Incorrect number of segments in '{}'",
+                line
+            )));
+        }
+        // Skip values that can't be parsed, like when reading the memory status
+        let value = match parse_u64(segments[1]) {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+
+        let field = match segments[0] {
+            "total_rss" => &mut stat.anon,
+            "total_cache" => &mut stat.file,
+            "total_shmem" => &mut stat.shmem,
+            "total_mapped_file" => &mut stat.file_mapped,
+            "total_dirty" => &mut stat.file_dirty,
+            "total_writeback" => &mut stat.file_writeback,
+            "total_rss_huge" => &mut stat.anon_thp,
+            "total_active_anon" => &mut stat.active_anon,
+            "total_inactive_anon" => &mut stat.inactive_anon,
+            "total_active_file" => &mut stat.active_file,
+            "total_inactive_file" => &mut stat.inactive_file,
+            "total_unevictable" => &mut stat.unevictable,
+            "total_pgfault" => &mut stat.pgfault,
+            "total_pgmajfault" => &mut stat.pgmajfault,
+            key => {
+                stat.other.insert(key.to_owned(), value);
+                continue;
+            }
+        };
+        *field = Some(value);
+    }
+
+    Ok(stat)
+}
+
+#[cfg(target_os = "linux")]
+pub fn read_and_parse_v1_sys_memory_events(path: &Path) -> Result<CgroupMemoryEventsMeasurement> {
+    let precise_time_ns = precise_time_ns();
+
+    let failcnt = read_file_value_as_u64(&path.join("memory.failcnt"))?;
+    let oom_control = read_key_value_file(&path.join("memory.oom_control"))?;
+
+    Ok(CgroupMemoryEventsMeasurement {
+        precise_time_ns,
+        events: CgroupMemoryEvents {
+            // The OOM kill count is only available since Linux 4.13
+            oom_kill: oom_control.get("oom_kill").copied(),
+            failcnt: Some(failcnt),
+            ..Default::default()
+        },
+        local_events: None,
+        under_oom: oom_control.get("under_oom").map(|value| *value == 1),
+    })
+}
+
+#[cfg(target_os = "linux")]
+pub fn read_and_parse_v1_sys_memory_thresholds(path: &Path) -> Result<CgroupMemoryThresholds> {
+    let soft_limit = read_file_value_as_u64(&path.join("memory.soft_limit_in_bytes"))?;
+
+    Ok(CgroupMemoryThresholds {
+        soft_limit: Some(soft_limit)
+            .filter(|soft_limit| *soft_limit < UNLIMITED)
+            .map(bytes_to_kilo_bytes),
+        ..Default::default()
+    })
+}
+
+#[cfg(target_os = "linux")]
+pub fn read_and_parse_v1_sys_hugetlb(path: &Path) -> Result<Vec<CgroupHugetlb>> {
+    let mut out = Vec::new();
+    for (label, page_size) in hugetlb_page_sizes(path, ".usage_in_bytes")? {
+        let usage =
+            read_file_value_as_u64(&path.join(format!("hugetlb.{}.usage_in_bytes", label)))?;
+        let limit =
+            read_file_value_as_u64(&path.join(format!("hugetlb.{}.limit_in_bytes", label)))?;
+        out.push(CgroupHugetlb {
+            page_size,
+            usage: bytes_to_kilo_bytes(usage),
+            limit: Some(limit)
+                .filter(|limit| *limit < UNLIMITED)
+                .map(bytes_to_kilo_bytes),
+        });
+    }
+
+    Ok(out)
+}
+
 #[cfg(test)]
 #[cfg(target_os = "linux")]
 mod tests {
+    use super::super::cgroup::{CgroupHugetlb, CgroupMemoryEvents, CgroupMemoryThresholds};
     use super::super::Memory;
     use crate::ProbeError;
     use std::path::Path;
@@ -75,7 +189,7 @@ mod tests {
     #[test]
     fn test_read_and_parse_v1_sys_memory() {
         let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/memory/");
-        let memory = super::read_and_parse_v1_sys_memory(&path).unwrap();
+        let memory = super::read_and_parse_v1_sys_memory(path).unwrap();
 
         let expected = Memory {
             total: Some(512000), // 500mb
@@ -87,6 +201,9 @@ mod tests {
             swap_total: Some(1_488_000), // reported swap total - reported memory total
             swap_free: Some(1_055_528),
             swap_used: Some(432_472), // reported swap used - (reported memory used, including cache)
+            rss: Some(1412),
+            working_set: Some(48152), // usage - inactive file
+            inconsistent: false,
         };
         assert_eq!(expected, memory);
         assert_eq!(memory.total.unwrap(), memory.used + memory.free.unwrap());
@@ -96,10 +213,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_read_and_parse_v1_sys_memory_nested() {
+        // The memory is used by the processes in child cgroups
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/memory_nested/");
+        let memory = super::read_and_parse_v1_sys_memory(path).unwrap();
+
+        assert_eq!(Some(58928), memory.cached);
+        assert_eq!(Some(4096), memory.shmem);
+        assert_eq!(8600, memory.used);
+        assert_eq!(Some(1412), memory.rss);
+        assert_eq!(Some(48152), memory.working_set);
+        assert!(!memory.inconsistent);
+    }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_memory_racy() {
+        // Cache and swap usage were read while the usage was dropping
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/memory_racy/");
+        let memory = super::read_and_parse_v1_sys_memory(path).unwrap();
+
+        let expected = Memory {
+            total: Some(512000),
+            free: Some(512000),
+            used: 0, // cache is larger than usage
+            buffers: None,
+            cached: Some(527_360),
+            shmem: Some(0),
+            swap_total: Some(536_576),
+            swap_free: Some(536_576),
+            swap_used: Some(0), // swap usage is lower than memory usage
+            rss: Some(1412),
+            working_set: Some(0), // inactive file is larger than usage
+            inconsistent: true,
+        };
+        assert_eq!(expected, memory);
+    }
+
     #[test]
     fn test_read_and_parse_v1_sys_memory_wrong_path() {
         let path = Path::new("/nonsense");
-        match super::read_and_parse_v1_sys_memory(&path) {
+        match super::read_and_parse_v1_sys_memory(path) {
             Err(ProbeError::IO(_, _)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -108,7 +262,7 @@ mod tests {
     #[test]
     fn test_read_and_parse_v1_sys_memory_incomplete() {
         let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/memory_incomplete/");
-        match super::read_and_parse_v1_sys_memory(&path) {
+        match super::read_and_parse_v1_sys_memory(path) {
             Err(ProbeError::UnexpectedContent(_)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -117,7 +271,7 @@ mod tests {
     #[test]
     fn test_read_and_parse_v1_sys_memory_missing_files() {
         let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/memory_missing_files/");
-        match super::read_and_parse_v1_sys_memory(&path) {
+        match super::read_and_parse_v1_sys_memory(path) {
             Err(ProbeError::IO(_, _)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -126,7 +280,7 @@ mod tests {
     #[test]
     fn test_read_and_parse_v1_sys_memory_garbage() {
         let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/memory_garbage/");
-        match super::read_and_parse_v1_sys_memory(&path) {
+        match super::read_and_parse_v1_sys_memory(path) {
             Err(ProbeError::UnexpectedContent(_)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -135,7 +289,7 @@ mod tests {
     #[test]
     fn test_read_and_parse_v1_sys_memory_no_swap() {
         let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/memory_without_swap/");
-        let memory = super::read_and_parse_v1_sys_memory(&path).unwrap();
+        let memory = super::read_and_parse_v1_sys_memory(path).unwrap();
 
         let expected = Memory {
             total: Some(512000), // 500mb
@@ -147,6 +301,9 @@ mod tests {
             swap_total: None, // Reads 0 swap
             swap_free: None,  // Reads 0 swap
             swap_used: None,
+            rss: Some(1412),
+            working_set: Some(48152),
+            inconsistent: false,
         };
         assert_eq!(expected, memory);
         assert_eq!(memory.total.unwrap(), memory.used + memory.free.unwrap());
@@ -154,4 +311,182 @@ mod tests {
         assert_eq!(memory.swap_free, None);
         assert_eq!(memory.swap_used, None);
     }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_memory_stat() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/memory/");
+        let stat = super::read_and_parse_v1_sys_memory_stat(path).unwrap();
+
+        assert_eq!(Some(1445888), stat.anon);
+        assert_eq!(Some(60342272), stat.file);
+        assert_eq!(None, stat.shmem);
+        assert_eq!(Some(3710976), stat.file_mapped);
+        assert_eq!(Some(0), stat.file_dirty);
+        assert_eq!(Some(0), stat.file_writeback);
+        assert_eq!(Some(0), stat.anon_thp);
+        assert_eq!(Some(1482752), stat.active_anon);
+        assert_eq!(Some(8192), stat.inactive_anon);
+        assert_eq!(Some(40439808), stat.active_file);
+        assert_eq!(Some(19841024), stat.inactive_file);
+        assert_eq!(Some(0), stat.unevictable);
+        assert_eq!(Some(87136), stat.pgfault);
+        assert_eq!(Some(149), stat.pgmajfault);
+        // Not reported by cgroups v1
+        assert_eq!(None, stat.kernel_stack);
+        assert_eq!(None, stat.workingset_refault_file);
+
+        // Non-hierarchical fields are kept in the map
+        assert_eq!(Some(&60342272), stat.other.get("cache"));
+        assert_eq!(
+            Some(&524288000),
+            stat.other.get("hierarchical_memory_limit")
+        );
+        assert_eq!(Some(&0), stat.other.get("total_swap"));
+    }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_memory_stat_wrong_path() {
+        let path = Path::new("/nonsense");
+        match super::read_and_parse_v1_sys_memory_stat(path) {
+            Err(ProbeError::IO(_, _)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_memory_stat_garbage() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/memory_garbage/");
+        let stat = super::read_and_parse_v1_sys_memory_stat(path).unwrap();
+
+        assert_eq!(Some(1445888), stat.anon);
+        assert_eq!(None, stat.other.get("swap"));
+        assert_eq!(Some(&2048000), stat.other.get("total_swap"));
+    }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_memory_events() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/memory/");
+        let measurement = super::read_and_parse_v1_sys_memory_events(path).unwrap();
+
+        assert!(measurement.precise_time_ns > 0);
+        assert_eq!(
+            CgroupMemoryEvents {
+                low: None,
+                high: None,
+                max: None,
+                oom: None,
+                oom_kill: Some(4),
+                failcnt: Some(87),
+            },
+            measurement.events
+        );
+        assert_eq!(None, measurement.local_events);
+        assert_eq!(Some(true), measurement.under_oom);
+    }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_memory_events_without_oom_kill() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/memory_without_swap/");
+        let measurement = super::read_and_parse_v1_sys_memory_events(path).unwrap();
+
+        assert_eq!(None, measurement.events.oom_kill);
+        assert_eq!(Some(0), measurement.events.failcnt);
+        assert_eq!(Some(false), measurement.under_oom);
+    }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_memory_events_wrong_path() {
+        let path = Path::new("/nonsense");
+        match super::read_and_parse_v1_sys_memory_events(path) {
+            Err(ProbeError::IO(_, _)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_memory_events_garbage() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/memory_garbage/");
+        match super::read_and_parse_v1_sys_memory_events(path) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_memory_thresholds() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/memory/");
+        let thresholds = super::read_and_parse_v1_sys_memory_thresholds(path).unwrap();
+
+        let expected = CgroupMemoryThresholds {
+            high: None,
+            low: None,
+            min: None,
+            soft_limit: Some(262_144), // 256mb
+        };
+        assert_eq!(expected, thresholds);
+    }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_memory_thresholds_unlimited() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/memory_without_swap/");
+        let thresholds = super::read_and_parse_v1_sys_memory_thresholds(path).unwrap();
+
+        assert_eq!(CgroupMemoryThresholds::default(), thresholds);
+    }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_memory_thresholds_wrong_path() {
+        let path = Path::new("/nonsense");
+        match super::read_and_parse_v1_sys_memory_thresholds(path) {
+            Err(ProbeError::IO(_, _)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_memory_thresholds_garbage() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/memory_garbage/");
+        match super::read_and_parse_v1_sys_memory_thresholds(path) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_hugetlb() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/hugetlb/");
+        let hugetlb = super::read_and_parse_v1_sys_hugetlb(path).unwrap();
+
+        let expected = vec![
+            CgroupHugetlb {
+                page_size: 2048,
+                usage: 16384,
+                limit: None,
+            },
+            CgroupHugetlb {
+                page_size: 1_048_576,
+                usage: 0,
+                limit: Some(2_097_152),
+            },
+        ];
+        assert_eq!(expected, hugetlb);
+    }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_hugetlb_wrong_path() {
+        let path = Path::new("/nonsense");
+        match super::read_and_parse_v1_sys_hugetlb(path) {
+            Err(ProbeError::IO(_, _)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_v1_sys_hugetlb_garbage() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v1/hugetlb_garbage/");
+        match super::read_and_parse_v1_sys_hugetlb(path) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
 }
@@ -1,9 +1,15 @@
 use std::io::BufRead;
 use std::path::Path;
 
-use super::Memory;
+use super::cgroup::{
+    CgroupHugetlb, CgroupMemoryEvents, CgroupMemoryEventsMeasurement, CgroupMemoryStat,
+    CgroupMemoryThresholds,
+};
+use super::{clamped_sub, hugetlb_page_sizes, Memory};
 use crate::{bytes_to_kilo_bytes, file_to_buf_reader, parse_u64, read_file_value_as_u64};
-use crate::{path_to_string, ProbeError, Result};
+use crate::{file_to_string, path_to_string, precise_time_ns, read_key_value_file};
+use crate::{ProbeError, Result};
+
 #[cfg(target_os = "linux")]
 pub fn read_and_parse_v2_sys_memory(path: &Path) -> Result<Memory> {
     let mut memory = Memory {
@@ -16,27 +22,33 @@ pub fn read_and_parse_v2_sys_memory(path: &Path) -> Result<Memory> {
         swap_total: None,
         swap_free: None,
         swap_used: None,
+        rss: None,
+        working_set: None,
+        inconsistent: false,
     };
 
     memory.total = read_file_value_as_u64(&path.join("memory.max"))
         .ok()
         .map(bytes_to_kilo_bytes);
 
-    memory.used = bytes_to_kilo_bytes(read_file_value_as_u64(&path.join("memory.current"))?);
+    // The files are not read at the same moment, so values calculated from multiple files are
+    // clamped at zero when the usage changed in between.
+    let mut inconsistent = false;
 
-    let reader = file_to_buf_reader(&path.join("memory.stat"))?;
-    for line_result in reader.lines() {
-        let line = line_result.map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
-        let segments: Vec<&str> = line.split_whitespace().collect();
-        let value = parse_u64(&segments[1])?;
+    let usage = read_file_value_as_u64(&path.join("memory.current"))?;
+    memory.used = bytes_to_kilo_bytes(usage);
 
-        if segments[0] == "shmem" {
-            memory.shmem = Some(bytes_to_kilo_bytes(value));
-            break;
-        };
-    }
+    let stat = read_and_parse_v2_sys_memory_stat(path)?;
+    memory.shmem = stat.shmem.map(bytes_to_kilo_bytes);
+    memory.cached = stat.file.map(bytes_to_kilo_bytes);
+    memory.rss = stat.anon.map(bytes_to_kilo_bytes);
+    memory.working_set = stat.inactive_file.map(|inactive_file| {
+        bytes_to_kilo_bytes(clamped_sub(usage, inactive_file, &mut inconsistent))
+    });
 
-    memory.free = memory.total.map(|total| total - memory.used);
+    memory.free = memory
+        .total
+        .map(|total| clamped_sub(total, memory.used, &mut inconsistent));
 
     memory.swap_total = read_file_value_as_u64(&path.join("memory.swap.max"))
         .ok()
@@ -47,14 +59,151 @@ pub fn read_and_parse_v2_sys_memory(path: &Path) -> Result<Memory> {
     memory.swap_free = memory
         .swap_total
         .zip(memory.swap_used)
-        .map(|(total, used)| total.saturating_sub(used));
+        .map(|(total, used)| clamped_sub(total, used, &mut inconsistent));
+
+    memory.inconsistent = inconsistent;
 
     Ok(memory)
 }
 
+#[cfg(target_os = "linux")]
+pub fn read_and_parse_v2_sys_memory_stat(path: &Path) -> Result<CgroupMemoryStat> {
+    let mut stat = CgroupMemoryStat::default();
+
+    let stat_path = path.join("memory.stat");
+    let reader = file_to_buf_reader(&stat_path)?;
+    for line_result in reader.lines() {
+        let line = line_result.map_err(|e| ProbeError::IO(e, path_to_string(&stat_path)))?;
+        let segments: Vec<&str> = line.split_whitespace().collect();
+        if segments.len() != 2 {
+            return Err(ProbeError::UnexpectedContent(format!(
+                "Incorrect number of segments in '{}'",
+                line
+            )));
+        }
+        let value = parse_u64(segments[1])?;
+
+        let field = match segments[0] {
+            "anon" => &mut stat.anon,
+            "file" => &mut stat.file,
+            "kernel_stack" => &mut stat.kernel_stack,
+            "slab" => &mut stat.slab,
+            "slab_reclaimable" => &mut stat.slab_reclaimable,
+            "slab_unreclaimable" => &mut stat.slab_unreclaimable,
+            "sock" => &mut stat.sock,
+            "shmem" => &mut stat.shmem,
+            "file_mapped" => &mut stat.file_mapped,
+            "file_dirty" => &mut stat.file_dirty,
+            "file_writeback" => &mut stat.file_writeback,
+            "anon_thp" => &mut stat.anon_thp,
+            "active_anon" => &mut stat.active_anon,
+            "inactive_anon" => &mut stat.inactive_anon,
+            "active_file" => &mut stat.active_file,
+            "inactive_file" => &mut stat.inactive_file,
+            "unevictable" => &mut stat.unevictable,
+            "pgfault" => &mut stat.pgfault,
+            "pgmajfault" => &mut stat.pgmajfault,
+            "workingset_refault_anon" => &mut stat.workingset_refault_anon,
+            "workingset_refault_file" => &mut stat.workingset_refault_file,
+            "workingset_activate_anon" => &mut stat.workingset_activate_anon,
+            "workingset_activate_file" => &mut stat.workingset_activate_file,
+            "workingset_restore_anon" => &mut stat.workingset_restore_anon,
+            "workingset_restore_file" => &mut stat.workingset_restore_file,
+            key => {
+                stat.other.insert(key.to_owned(), value);
+                continue;
+            }
+        };
+        *field = Some(value);
+    }
+
+    Ok(stat)
+}
+
+#[cfg(target_os = "linux")]
+pub fn read_and_parse_v2_sys_memory_events(path: &Path) -> Result<CgroupMemoryEventsMeasurement> {
+    let precise_time_ns = precise_time_ns();
+
+    let events = read_and_parse_v2_events_file(&path.join("memory.events"))?;
+
+    // Only available since Linux 5.2
+    let local_path = path.join("memory.events.local");
+    let local_events = if local_path.exists() {
+        Some(read_and_parse_v2_events_file(&local_path)?)
+    } else {
+        None
+    };
+
+    Ok(CgroupMemoryEventsMeasurement {
+        precise_time_ns,
+        events,
+        local_events,
+        under_oom: None,
+    })
+}
+
+#[inline]
+fn read_and_parse_v2_events_file(path: &Path) -> Result<CgroupMemoryEvents> {
+    let values = read_key_value_file(path)?;
+
+    Ok(CgroupMemoryEvents {
+        low: values.get("low").copied(),
+        high: values.get("high").copied(),
+        max: values.get("max").copied(),
+        oom: values.get("oom").copied(),
+        oom_kill: values.get("oom_kill").copied(),
+        failcnt: None,
+    })
+}
+
+#[cfg(target_os = "linux")]
+pub fn read_and_parse_v2_sys_memory_thresholds(path: &Path) -> Result<CgroupMemoryThresholds> {
+    Ok(CgroupMemoryThresholds {
+        high: read_and_parse_v2_threshold(&path.join("memory.high"))?,
+        low: read_and_parse_v2_protection(&path.join("memory.low"))?,
+        min: read_and_parse_v2_protection(&path.join("memory.min"))?,
+        soft_limit: None,
+    })
+}
+
+#[inline]
+fn read_and_parse_v2_threshold(path: &Path) -> Result<Option<u64>> {
+    // Not present for the root cgroup, and `memory.min` only since Linux 4.18
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    match file_to_string(path)?.trim() {
+        "max" => Ok(None),
+        value => parse_u64(value).map(|value| Some(bytes_to_kilo_bytes(value))),
+    }
+}
+
+#[inline]
+fn read_and_parse_v2_protection(path: &Path) -> Result<Option<u64>> {
+    // Unlike the thresholds, an unset `memory.low` or `memory.min` protection is 0
+    Ok(read_and_parse_v2_threshold(path)?.filter(|value| *value > 0))
+}
+
+#[cfg(target_os = "linux")]
+pub fn read_and_parse_v2_sys_hugetlb(path: &Path) -> Result<Vec<CgroupHugetlb>> {
+    let mut out = Vec::new();
+    for (label, page_size) in hugetlb_page_sizes(path, ".current")? {
+        let usage = read_file_value_as_u64(&path.join(format!("hugetlb.{}.current", label)))?;
+        out.push(CgroupHugetlb {
+            page_size,
+            usage: bytes_to_kilo_bytes(usage),
+            limit: read_and_parse_v2_threshold(&path.join(format!("hugetlb.{}.max", label)))?,
+        });
+    }
+
+    Ok(out)
+}
+
 #[cfg(test)]
 #[cfg(target_os = "linux")]
 mod tests {
+    use super::super::cgroup::{CgroupHugetlb, CgroupMemoryEvents, CgroupMemoryThresholds};
     use super::super::Memory;
     use crate::ProbeError;
     use std::path::Path;
@@ -62,18 +211,21 @@ mod tests {
     #[test]
     fn test_read_and_parse_v2_sys_memory() {
         let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/memory/");
-        let memory = super::read_and_parse_v2_sys_memory(&path).unwrap();
+        let memory = super::read_and_parse_v2_sys_memory(path).unwrap();
 
         let expected = Memory {
             total: Some(512000), // 500mb
             free: Some(444472),  // total - used
             used: 67528,
             buffers: None,
-            cached: None,
+            cached: Some(0),
             shmem: Some(0),
             swap_total: Some(2000000),  // reported swap total
             swap_free: Some(1_500_000), // swap total - swap used
             swap_used: Some(500_000),   // reported swap used
+            rss: Some(0),
+            working_set: Some(67528), // usage - inactive file
+            inconsistent: false,
         };
         assert_eq!(expected, memory);
         assert_eq!(memory.total.unwrap(), memory.used + memory.free.unwrap());
@@ -83,10 +235,55 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_read_and_parse_v2_sys_memory_working_set() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/memory_stat/");
+        let memory = super::read_and_parse_v2_sys_memory(path).unwrap();
+
+        let expected = Memory {
+            total: Some(1_048_576),
+            free: Some(434_176),
+            used: 614_400,
+            buffers: None,
+            cached: Some(307_200),
+            shmem: Some(16_384),
+            swap_total: None,
+            swap_free: None,
+            swap_used: None,
+            rss: Some(204_800),
+            working_set: Some(409_600), // usage - inactive file
+            inconsistent: false,
+        };
+        assert_eq!(expected, memory);
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_memory_racy() {
+        // Usage was read while briefly exceeding the limits
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/memory_racy/");
+        let memory = super::read_and_parse_v2_sys_memory(path).unwrap();
+
+        let expected = Memory {
+            total: Some(512000),
+            free: Some(0), // usage is larger than the limit
+            used: 518_144,
+            buffers: None,
+            cached: Some(527_360),
+            shmem: Some(0),
+            swap_total: Some(1024),
+            swap_free: Some(0), // swap usage is larger than the swap limit
+            swap_used: Some(2048),
+            rss: Some(1412),
+            working_set: Some(0), // inactive file is larger than usage
+            inconsistent: true,
+        };
+        assert_eq!(expected, memory);
+    }
+
     #[test]
     fn test_read_and_parse_v2_sys_memory_wrong_path() {
         let path = Path::new("/nonsense");
-        match super::read_and_parse_v2_sys_memory(&path) {
+        match super::read_and_parse_v2_sys_memory(path) {
             Err(ProbeError::IO(_, _)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -95,7 +292,7 @@ mod tests {
     #[test]
     fn test_read_and_parse_v2_sys_memory_incomplete() {
         let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/memory_incomplete/");
-        match super::read_and_parse_v2_sys_memory(&path) {
+        match super::read_and_parse_v2_sys_memory(path) {
             Err(ProbeError::UnexpectedContent(_)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -104,7 +301,7 @@ mod tests {
     #[test]
     fn test_read_and_parse_v1_sys_memory_missing_files() {
         let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/memory_missing_files/");
-        match super::read_and_parse_v2_sys_memory(&path) {
+        match super::read_and_parse_v2_sys_memory(path) {
             Err(ProbeError::IO(_, _)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -113,7 +310,7 @@ mod tests {
     #[test]
     fn test_read_and_parse_v1_sys_memory_garbage() {
         let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/memory_garbage/");
-        match super::read_and_parse_v2_sys_memory(&path) {
+        match super::read_and_parse_v2_sys_memory(path) {
             Err(ProbeError::UnexpectedContent(_)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -122,18 +319,21 @@ mod tests {
     #[test]
     fn test_read_and_parse_v1_sys_memory_no_swap() {
         let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/memory_without_swap/");
-        let memory = super::read_and_parse_v2_sys_memory(&path).unwrap();
+        let memory = super::read_and_parse_v2_sys_memory(path).unwrap();
 
         let expected = Memory {
             total: Some(512000), // 500mb
             free: Some(444472),  // total - used
             used: 67528,
             buffers: None,
-            cached: None,
+            cached: Some(0),
             shmem: Some(0),
             swap_total: None, // Reads 0 swap
             swap_free: None,  // Reads 0 swap
             swap_used: None,
+            rss: Some(0),
+            working_set: Some(67528), // usage - inactive file
+            inconsistent: false,
         };
         assert_eq!(expected, memory);
         assert_eq!(memory.total.unwrap(), memory.used + memory.free.unwrap());
@@ -141,4 +341,208 @@ mod tests {
         assert_eq!(memory.swap_free, None);
         assert_eq!(memory.swap_used, None);
     }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_memory_stat() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/memory_stat/");
+        let stat = super::read_and_parse_v2_sys_memory_stat(path).unwrap();
+
+        assert_eq!(Some(209715200), stat.anon);
+        assert_eq!(Some(314572800), stat.file);
+        assert_eq!(Some(1048576), stat.kernel_stack);
+        assert_eq!(Some(8912896), stat.slab);
+        assert_eq!(Some(6291456), stat.slab_reclaimable);
+        assert_eq!(Some(2621440), stat.slab_unreclaimable);
+        assert_eq!(Some(4096), stat.sock);
+        assert_eq!(Some(16777216), stat.shmem);
+        assert_eq!(Some(52428800), stat.file_mapped);
+        assert_eq!(Some(1048576), stat.file_dirty);
+        assert_eq!(Some(0), stat.file_writeback);
+        assert_eq!(Some(67108864), stat.anon_thp);
+        assert_eq!(Some(37748736), stat.active_anon);
+        assert_eq!(Some(188743680), stat.inactive_anon);
+        assert_eq!(Some(88080384), stat.active_file);
+        assert_eq!(Some(209715200), stat.inactive_file);
+        assert_eq!(Some(0), stat.unevictable);
+        assert_eq!(Some(1048576), stat.pgfault);
+        assert_eq!(Some(321), stat.pgmajfault);
+        assert_eq!(Some(12), stat.workingset_refault_anon);
+        assert_eq!(Some(3456), stat.workingset_refault_file);
+        assert_eq!(Some(3), stat.workingset_activate_anon);
+        assert_eq!(Some(789), stat.workingset_activate_file);
+        assert_eq!(Some(1), stat.workingset_restore_anon);
+        assert_eq!(Some(234), stat.workingset_restore_file);
+
+        assert_eq!(Some(&2097152), stat.other.get("pagetables"));
+        assert_eq!(Some(&10240), stat.other.get("pgscan"));
+        assert_eq!(Some(&32), stat.other.get("thp_fault_alloc"));
+        assert_eq!(23, stat.other.len());
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_memory_stat_idle() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/memory/");
+        let stat = super::read_and_parse_v2_sys_memory_stat(path).unwrap();
+
+        assert_eq!(Some(0), stat.anon);
+        assert_eq!(Some(49152), stat.kernel_stack);
+        assert_eq!(Some(0), stat.shmem);
+        assert_eq!(Some(1122), stat.pgfault);
+        assert_eq!(Some(&0), stat.other.get("percpu"));
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_memory_stat_wrong_path() {
+        let path = Path::new("/nonsense");
+        match super::read_and_parse_v2_sys_memory_stat(path) {
+            Err(ProbeError::IO(_, _)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_memory_stat_garbage() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/memory_stat_garbage/");
+        match super::read_and_parse_v2_sys_memory_stat(path) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_memory_events() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/memory/");
+        let measurement = super::read_and_parse_v2_sys_memory_events(path).unwrap();
+
+        assert!(measurement.precise_time_ns > 0);
+        assert_eq!(
+            CgroupMemoryEvents {
+                low: Some(0),
+                high: Some(12),
+                max: Some(40),
+                oom: Some(3),
+                oom_kill: Some(2),
+                failcnt: None,
+            },
+            measurement.events
+        );
+        assert_eq!(
+            Some(CgroupMemoryEvents {
+                low: Some(0),
+                high: Some(5),
+                max: Some(21),
+                oom: Some(1),
+                oom_kill: Some(1),
+                failcnt: None,
+            }),
+            measurement.local_events
+        );
+        assert_eq!(None, measurement.under_oom);
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_memory_events_without_local() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/memory_without_swap/");
+        let measurement = super::read_and_parse_v2_sys_memory_events(path).unwrap();
+
+        assert_eq!(Some(0), measurement.events.oom_kill);
+        assert_eq!(None, measurement.local_events);
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_memory_events_wrong_path() {
+        let path = Path::new("/nonsense");
+        match super::read_and_parse_v2_sys_memory_events(path) {
+            Err(ProbeError::IO(_, _)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_memory_events_garbage() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/memory_garbage/");
+        match super::read_and_parse_v2_sys_memory_events(path) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_memory_thresholds() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/memory/");
+        let thresholds = super::read_and_parse_v2_sys_memory_thresholds(path).unwrap();
+
+        // `memory.min` is 0, which means it's not set
+        assert_eq!(None, thresholds.min);
+        let expected = CgroupMemoryThresholds {
+            high: Some(460_800), // 450mb
+            low: Some(102_400),  // 100mb
+            min: None,
+            soft_limit: None,
+        };
+        assert_eq!(expected, thresholds);
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_memory_thresholds_unlimited() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/memory_without_swap/");
+        let thresholds = super::read_and_parse_v2_sys_memory_thresholds(path).unwrap();
+
+        // `memory.high` is set to max, the other files are not present
+        assert_eq!(CgroupMemoryThresholds::default(), thresholds);
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_memory_thresholds_garbage() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/memory_garbage/");
+        match super::read_and_parse_v2_sys_memory_thresholds(path) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_hugetlb() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/hugetlb/");
+        let hugetlb = super::read_and_parse_v2_sys_hugetlb(path).unwrap();
+
+        let expected = vec![
+            CgroupHugetlb {
+                page_size: 2048,
+                usage: 16384,
+                limit: None,
+            },
+            CgroupHugetlb {
+                page_size: 1_048_576,
+                usage: 0,
+                limit: Some(2_097_152),
+            },
+        ];
+        assert_eq!(expected, hugetlb);
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_hugetlb_not_available() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/memory/");
+        let hugetlb = super::read_and_parse_v2_sys_hugetlb(path).unwrap();
+        assert!(hugetlb.is_empty());
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_hugetlb_wrong_path() {
+        let path = Path::new("/nonsense");
+        match super::read_and_parse_v2_sys_hugetlb(path) {
+            Err(ProbeError::IO(_, _)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_v2_sys_hugetlb_garbage() {
+        let path = Path::new("fixtures/linux/sys/fs/cgroup_v2/hugetlb_garbage/");
+        match super::read_and_parse_v2_sys_hugetlb(path) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
 }
@@ -0,0 +1,211 @@
+use crate::Result;
+
+/// A pool of huge pages of a single size from `/sys/kernel/mm/hugepages`. The totals of the
+/// default pool are also reported by `memory::proc::read_meminfo`.
+#[derive(Debug, PartialEq)]
+pub struct HugePagePool {
+    /// Size of a page in KB
+    pub page_size: u64,
+    /// Number of pages in the pool
+    pub total: u64,
+    pub free: u64,
+    /// Pages promised to a mapping, but not yet allocated
+    pub reserved: u64,
+    /// Pages allocated above `total` through overcommitting
+    pub surplus: u64,
+    /// Maximum number of surplus pages
+    pub overcommit: u64,
+}
+
+/// Mode of transparent huge pages
+#[derive(Debug, PartialEq)]
+pub enum TransparentHugePageMode {
+    /// Used for all memory
+    Always,
+    /// Only used for memory regions which requested it through `madvise`
+    Madvise,
+    Never,
+}
+
+/// Read the huge page pools of all supported page sizes. Kernels without huge page support
+/// have no pools.
+#[cfg(target_os = "linux")]
+pub fn read_pools() -> Result<Vec<HugePagePool>> {
+    os::read_pools()
+}
+
+/// Read the configured mode of transparent huge pages. Returns `None` for kernels without
+/// transparent huge page support.
+#[cfg(target_os = "linux")]
+pub fn read_transparent_mode() -> Result<Option<TransparentHugePageMode>> {
+    os::read_transparent_mode()
+}
+
+#[cfg(target_os = "linux")]
+mod os {
+    use std::fs;
+    use std::path::Path;
+
+    use super::{HugePagePool, TransparentHugePageMode};
+    use crate::{
+        dir_exists, file_to_string, parse_u64, path_to_string, read_file_value_as_u64, ProbeError,
+        Result,
+    };
+
+    #[inline]
+    pub fn read_pools() -> Result<Vec<HugePagePool>> {
+        read_and_parse_pools(Path::new("/sys/kernel/mm/hugepages"))
+    }
+
+    #[inline]
+    pub fn read_transparent_mode() -> Result<Option<TransparentHugePageMode>> {
+        read_and_parse_transparent_mode(Path::new("/sys/kernel/mm/transparent_hugepage/enabled"))
+    }
+
+    pub fn read_and_parse_pools(path: &Path) -> Result<Vec<HugePagePool>> {
+        if !dir_exists(path) {
+            return Ok(Vec::new());
+        }
+
+        let entries = fs::read_dir(path).map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
+
+        let mut out = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
+            // Directories are named after the page size, such as `hugepages-2048kB`
+            let file_name = entry.file_name();
+            let page_size = match file_name
+                .to_str()
+                .and_then(|name| name.strip_prefix("hugepages-"))
+                .and_then(|name| name.strip_suffix("kB"))
+            {
+                Some(page_size) => parse_u64(page_size)?,
+                None => continue,
+            };
+
+            let pool_path = entry.path();
+            out.push(HugePagePool {
+                page_size,
+                total: read_file_value_as_u64(&pool_path.join("nr_hugepages"))?,
+                free: read_file_value_as_u64(&pool_path.join("free_hugepages"))?,
+                reserved: read_file_value_as_u64(&pool_path.join("resv_hugepages"))?,
+                surplus: read_file_value_as_u64(&pool_path.join("surplus_hugepages"))?,
+                overcommit: read_file_value_as_u64(&pool_path.join("nr_overcommit_hugepages"))?,
+            });
+        }
+        out.sort_by_key(|pool| pool.page_size);
+
+        Ok(out)
+    }
+
+    pub fn read_and_parse_transparent_mode(path: &Path) -> Result<Option<TransparentHugePageMode>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        // The active mode is marked with brackets, such as `always [madvise] never`
+        let contents = file_to_string(path)?;
+        let active = contents
+            .split_whitespace()
+            .find(|mode| mode.starts_with('[') && mode.ends_with(']'));
+
+        match active {
+            Some("[always]") => Ok(Some(TransparentHugePageMode::Always)),
+            Some("[madvise]") => Ok(Some(TransparentHugePageMode::Madvise)),
+            Some("[never]") => Ok(Some(TransparentHugePageMode::Never)),
+            _ => Err(ProbeError::UnexpectedContent(format!(
+                "Could not find the active mode in '{}'",
+                contents.trim()
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::os::{read_and_parse_pools, read_and_parse_transparent_mode};
+    use super::{HugePagePool, TransparentHugePageMode};
+    use crate::ProbeError;
+    use std::path::Path;
+
+    #[test]
+    fn test_read_pools() {
+        assert!(super::read_pools().is_ok());
+    }
+
+    #[test]
+    fn test_read_transparent_mode() {
+        assert!(super::read_transparent_mode().is_ok());
+    }
+
+    #[test]
+    fn test_read_and_parse_pools() {
+        let pools =
+            read_and_parse_pools(Path::new("fixtures/linux/sys/kernel/mm/hugepages")).unwrap();
+
+        let expected = vec![
+            HugePagePool {
+                page_size: 2048,
+                total: 512,
+                free: 256,
+                reserved: 16,
+                surplus: 0,
+                overcommit: 0,
+            },
+            HugePagePool {
+                page_size: 1048576,
+                total: 2,
+                free: 1,
+                reserved: 0,
+                surplus: 0,
+                overcommit: 0,
+            },
+        ];
+        assert_eq!(expected, pools);
+    }
+
+    #[test]
+    fn test_read_and_parse_pools_missing_dir() {
+        let pools = read_and_parse_pools(Path::new("/nonsense")).unwrap();
+        assert!(pools.is_empty());
+    }
+
+    #[test]
+    fn test_read_and_parse_pools_garbage() {
+        match read_and_parse_pools(Path::new("fixtures/linux/sys/kernel/mm/hugepages_garbage")) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_transparent_mode() {
+        let path = Path::new("fixtures/linux/sys/kernel/mm/transparent_hugepage/enabled");
+        assert_eq!(
+            Some(TransparentHugePageMode::Madvise),
+            read_and_parse_transparent_mode(path).unwrap()
+        );
+
+        let path = Path::new("fixtures/linux/sys/kernel/mm/transparent_hugepage/enabled_never");
+        assert_eq!(
+            Some(TransparentHugePageMode::Never),
+            read_and_parse_transparent_mode(path).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_read_and_parse_transparent_mode_missing_file() {
+        let mode = read_and_parse_transparent_mode(Path::new("/nonsense")).unwrap();
+        assert_eq!(None, mode);
+    }
+
+    #[test]
+    fn test_read_and_parse_transparent_mode_garbage() {
+        let path = Path::new("fixtures/linux/sys/kernel/mm/transparent_hugepage/enabled_garbage");
+        match read_and_parse_transparent_mode(path) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+}
@@ -1,6 +1,13 @@
+use std::fs;
+use std::path::Path;
+
+use crate::{path_to_string, ProbeError, Result};
+
 pub mod cgroup;
 mod cgroup_v1;
 mod cgroup_v2;
+pub mod hugepages;
+pub mod numa;
 pub mod proc;
 
 #[derive(Debug, PartialEq)]
@@ -14,4 +21,79 @@ pub struct Memory {
     pub swap_total: Option<u64>,
     pub swap_free: Option<u64>,
     pub swap_used: Option<u64>,
+    /// Anonymous memory of the container, not available for the system
+    pub rss: Option<u64>,
+    /// Memory usage minus inactive page cache, as used by the Kubernetes kubelet for evictions.
+    /// Not available for the system.
+    pub working_set: Option<u64>,
+    /// Some values were clamped to zero because the files they were calculated from were not
+    /// read at the same moment, such as the usage briefly exceeding the limit. The values are
+    /// usable, but might be slightly off.
+    pub inconsistent: bool,
+}
+
+/// Subtract two values that might have been read at slightly different moments, clamping the
+/// result at zero. Marks the values as inconsistent when the subtraction would underflow.
+#[inline]
+fn clamped_sub(value: u64, subtrahend: u64, inconsistent: &mut bool) -> u64 {
+    if subtrahend > value {
+        *inconsistent = true;
+        0
+    } else {
+        value - subtrahend
+    }
+}
+
+/// List the huge page sizes of the cgroup `hugetlb` files ending with a suffix, such as
+/// `hugetlb.2MB.current`. Returns the size as used in the file name and the size in KB, ordered
+/// by size.
+#[cfg(target_os = "linux")]
+fn hugetlb_page_sizes(path: &Path, suffix: &str) -> Result<Vec<(String, u64)>> {
+    let entries = fs::read_dir(path).map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
+
+    let mut out = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
+        let file_name = entry.file_name();
+        let label = match file_name
+            .to_str()
+            .and_then(|name| name.strip_prefix("hugetlb."))
+            .and_then(|name| name.strip_suffix(suffix))
+        {
+            Some(label) => label,
+            None => continue,
+        };
+
+        let (number, multiplier) = if let Some(number) = label.strip_suffix("KB") {
+            (number, 1)
+        } else if let Some(number) = label.strip_suffix("MB") {
+            (number, 1024)
+        } else if let Some(number) = label.strip_suffix("GB") {
+            (number, 1024 * 1024)
+        } else {
+            continue;
+        };
+
+        // Skips other files with the same suffix, such as `hugetlb.2MB.rsvd.current`
+        if let Ok(number) = number.parse::<u64>() {
+            out.push((label.to_owned(), number * multiplier));
+        }
+    }
+    out.sort_by_key(|(_, page_size)| *page_size);
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_clamped_sub() {
+        let mut inconsistent = false;
+        assert_eq!(100, super::clamped_sub(300, 200, &mut inconsistent));
+        assert_eq!(0, super::clamped_sub(200, 200, &mut inconsistent));
+        assert!(!inconsistent);
+
+        assert_eq!(0, super::clamped_sub(200, 300, &mut inconsistent));
+        assert!(inconsistent);
+    }
 }
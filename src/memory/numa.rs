@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+
+use crate::{calculate_time_difference, per_minute_value, FieldKind, ProbeError, Result};
+
+pub type NumaNodes = HashMap<String, NumaNode>;
+
+/// Measurement of the memory of all NUMA nodes at a certain time.
+#[derive(Debug, PartialEq)]
+pub struct NumaMeasurement {
+    pub precise_time_ns: u64,
+    pub nodes: NumaNodes,
+}
+
+impl NumaMeasurement {
+    /// Calculate the NUMA allocation statistics per minute based on this measurement and a
+    /// measurement in the future. It is advisable to make the next measurement roughly a minute
+    /// from this one for the most reliable result.
+    pub fn calculate_per_minute(
+        &self,
+        next_measurement: &NumaMeasurement,
+    ) -> Result<NumaPerMinute> {
+        let time_difference =
+            calculate_time_difference(self.precise_time_ns, next_measurement.precise_time_ns)?;
+
+        let mut nodes = HashMap::new();
+
+        for (name, node) in self.nodes.iter() {
+            let next_stat = match next_measurement.nodes.get(name) {
+                Some(node) => &node.stat,
+                None => {
+                    return Err(ProbeError::UnexpectedContent(format!(
+                        "{} is not present in the next measurement",
+                        name
+                    )))
+                }
+            };
+            let stat = &node.stat;
+
+            nodes.insert(
+                name.to_owned(),
+                NumaStat {
+                    numa_hit: per_minute_value(
+                        "numa_hit",
+                        FieldKind::Counter,
+                        next_stat.numa_hit,
+                        stat.numa_hit,
+                        time_difference,
+                    )?,
+                    numa_miss: per_minute_value(
+                        "numa_miss",
+                        FieldKind::Counter,
+                        next_stat.numa_miss,
+                        stat.numa_miss,
+                        time_difference,
+                    )?,
+                    numa_foreign: per_minute_value(
+                        "numa_foreign",
+                        FieldKind::Counter,
+                        next_stat.numa_foreign,
+                        stat.numa_foreign,
+                        time_difference,
+                    )?,
+                    interleave_hit: per_minute_value(
+                        "interleave_hit",
+                        FieldKind::Counter,
+                        next_stat.interleave_hit,
+                        stat.interleave_hit,
+                        time_difference,
+                    )?,
+                    local_node: per_minute_value(
+                        "local_node",
+                        FieldKind::Counter,
+                        next_stat.local_node,
+                        stat.local_node,
+                        time_difference,
+                    )?,
+                    other_node: per_minute_value(
+                        "other_node",
+                        FieldKind::Counter,
+                        next_stat.other_node,
+                        stat.other_node,
+                        time_difference,
+                    )?,
+                },
+            );
+        }
+
+        Ok(NumaPerMinute { nodes })
+    }
+}
+
+/// Memory usage and allocation statistics of a single NUMA node, such as `node0`.
+#[derive(Debug, PartialEq)]
+pub struct NumaNode {
+    pub memory: NumaNodeMemory,
+    pub stat: NumaStat,
+}
+
+/// Memory of a NUMA node from its `meminfo` file. Values are in KB.
+#[derive(Debug, PartialEq)]
+pub struct NumaNodeMemory {
+    pub total: u64,
+    pub free: u64,
+    pub used: u64,
+    pub file_pages: Option<u64>,
+    pub anon_pages: Option<u64>,
+    pub shmem: Option<u64>,
+    /// Fields without a dedicated field in this struct, keyed by their name in `meminfo`.
+    /// The `HugePages_*` fields are a number of pages.
+    pub other: HashMap<String, u64>,
+}
+
+/// Page allocation counters of a NUMA node from its `numastat` file.
+#[derive(Debug, Default, PartialEq)]
+pub struct NumaStat {
+    /// Pages allocated on this node as intended
+    pub numa_hit: u64,
+    /// Pages allocated on this node while intended for another node
+    pub numa_miss: u64,
+    /// Pages intended for this node, but allocated on another node
+    pub numa_foreign: u64,
+    pub interleave_hit: u64,
+    /// Pages allocated on this node while the process was running on it
+    pub local_node: u64,
+    /// Pages allocated on this node while the process was running on another node
+    pub other_node: u64,
+}
+
+/// NUMA allocation statistics of all nodes for a certain minute, calculated based on two
+/// measurements.
+#[derive(Debug, PartialEq)]
+pub struct NumaPerMinute {
+    pub nodes: HashMap<String, NumaStat>,
+}
+
+/// Read the current memory usage and allocation statistics of all NUMA nodes. Systems without
+/// NUMA support have no nodes.
+#[cfg(target_os = "linux")]
+pub fn read() -> Result<NumaMeasurement> {
+    os::read()
+}
+
+#[cfg(target_os = "linux")]
+mod os {
+    use std::collections::HashMap;
+    use std::io::BufRead;
+    use std::path::Path;
+
+    use super::{NumaMeasurement, NumaNode, NumaNodeMemory, NumaNodes, NumaStat};
+    use crate::{
+        dir_exists, file_to_buf_reader, numbered_dir_entries, parse_u64, path_to_string,
+        precise_time_ns, read_key_value_file, take_required_value, ProbeError, Result,
+    };
+
+    #[inline]
+    pub fn read() -> Result<NumaMeasurement> {
+        read_and_parse_numa(Path::new("/sys/devices/system/node"))
+    }
+
+    pub fn read_and_parse_numa(path: &Path) -> Result<NumaMeasurement> {
+        let precise_time_ns = precise_time_ns();
+
+        let mut nodes = NumaNodes::new();
+
+        // Kernels built without NUMA support have no node directory
+        if !dir_exists(path) {
+            return Ok(NumaMeasurement {
+                precise_time_ns,
+                nodes,
+            });
+        }
+
+        for (number, node_path) in numbered_dir_entries(path, "node")? {
+            nodes.insert(
+                format!("node{}", number),
+                NumaNode {
+                    memory: read_and_parse_node_meminfo(&node_path.join("meminfo"))?,
+                    stat: read_and_parse_numastat(&node_path.join("numastat"))?,
+                },
+            );
+        }
+
+        Ok(NumaMeasurement {
+            precise_time_ns,
+            nodes,
+        })
+    }
+
+    fn read_and_parse_node_meminfo(path: &Path) -> Result<NumaNodeMemory> {
+        let mut other = HashMap::new();
+
+        let reader = file_to_buf_reader(path)?;
+        for line_result in reader.lines() {
+            let line = line_result.map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
+            // Lines are formatted as `Node 0 MemTotal:  16310548 kB`
+            let segments: Vec<&str> = line.split_whitespace().collect();
+            if segments.len() < 4 {
+                return Err(ProbeError::UnexpectedContent(format!(
+                    "Incorrect number of segments in '{}'",
+                    line
+                )));
+            }
+            let value = parse_u64(segments[3])?;
+
+            other.insert(segments[2].trim_end_matches(':').to_owned(), value);
+        }
+
+        Ok(NumaNodeMemory {
+            total: take_required_value(&mut other, "MemTotal")?,
+            free: take_required_value(&mut other, "MemFree")?,
+            used: take_required_value(&mut other, "MemUsed")?,
+            file_pages: other.remove("FilePages"),
+            anon_pages: other.remove("AnonPages"),
+            shmem: other.remove("Shmem"),
+            other,
+        })
+    }
+
+    fn read_and_parse_numastat(path: &Path) -> Result<NumaStat> {
+        let mut values = read_key_value_file(path)?;
+
+        Ok(NumaStat {
+            numa_hit: take_required_value(&mut values, "numa_hit")?,
+            numa_miss: take_required_value(&mut values, "numa_miss")?,
+            numa_foreign: take_required_value(&mut values, "numa_foreign")?,
+            interleave_hit: take_required_value(&mut values, "interleave_hit")?,
+            local_node: take_required_value(&mut values, "local_node")?,
+            other_node: take_required_value(&mut values, "other_node")?,
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::os::read_and_parse_numa;
+    use super::{NumaMeasurement, NumaNode, NumaNodeMemory, NumaNodes, NumaStat};
+    use crate::ProbeError;
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    #[test]
+    fn test_read() {
+        assert!(super::read().is_ok());
+    }
+
+    #[test]
+    fn test_read_and_parse_numa() {
+        let measurement =
+            read_and_parse_numa(Path::new("fixtures/linux/sys/devices/system/node")).unwrap();
+
+        assert!(measurement.precise_time_ns > 0);
+        assert_eq!(2, measurement.nodes.len());
+
+        let node0 = measurement.nodes.get("node0").unwrap();
+        assert_eq!(16310548, node0.memory.total);
+        assert_eq!(2048000, node0.memory.free);
+        assert_eq!(14262548, node0.memory.used);
+        assert_eq!(Some(1936456), node0.memory.file_pages);
+        assert_eq!(Some(11534336), node0.memory.anon_pages);
+        assert_eq!(Some(9288), node0.memory.shmem);
+        assert_eq!(Some(&1333736), node0.memory.other.get("Inactive(file)"));
+        assert_eq!(Some(&0), node0.memory.other.get("HugePages_Total"));
+        assert_eq!(
+            NumaStat {
+                numa_hit: 4013320,
+                numa_miss: 0,
+                numa_foreign: 1200,
+                interleave_hit: 1023,
+                local_node: 4013320,
+                other_node: 0,
+            },
+            node0.stat
+        );
+
+        let node1 = measurement.nodes.get("node1").unwrap();
+        assert_eq!(12582912, node1.memory.free);
+        assert_eq!(1200, node1.stat.numa_miss);
+        assert_eq!(11300, node1.stat.other_node);
+    }
+
+    #[test]
+    fn test_read_and_parse_numa_missing_dir() {
+        let measurement = read_and_parse_numa(Path::new("/nonsense")).unwrap();
+        assert!(measurement.nodes.is_empty());
+    }
+
+    #[test]
+    fn test_read_and_parse_numa_garbage() {
+        match read_and_parse_numa(Path::new("fixtures/linux/sys/devices/system/node_garbage")) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_numa_incomplete() {
+        match read_and_parse_numa(Path::new(
+            "fixtures/linux/sys/devices/system/node_incomplete",
+        )) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_full_minute() {
+        let measurement1 = helpers::measurement(60_000_000_000, 1000, 20);
+        let measurement2 = helpers::measurement(120_000_000_000, 7000, 80);
+
+        let per_minute = measurement1.calculate_per_minute(&measurement2).unwrap();
+        let stat = per_minute.nodes.get("node0").unwrap();
+        assert_eq!(6000, stat.numa_hit);
+        assert_eq!(60, stat.numa_miss);
+    }
+
+    #[test]
+    fn test_calculate_per_minute_partial_minute() {
+        let measurement1 = helpers::measurement(60_000_000_000, 1000, 20);
+        let measurement2 = helpers::measurement(90_000_000_000, 7000, 80);
+
+        let per_minute = measurement1.calculate_per_minute(&measurement2).unwrap();
+        let stat = per_minute.nodes.get("node0").unwrap();
+        assert_eq!(12000, stat.numa_hit);
+        assert_eq!(120, stat.numa_miss);
+    }
+
+    #[test]
+    fn test_calculate_per_minute_wrong_times() {
+        let measurement1 = helpers::measurement(90_000_000_000, 1000, 20);
+        let measurement2 = helpers::measurement(60_000_000_000, 7000, 80);
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::InvalidInput(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_values_lower() {
+        let measurement1 = helpers::measurement(60_000_000_000, 1000, 20);
+        let measurement2 = helpers::measurement(120_000_000_000, 900, 80);
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_different_nodes() {
+        let measurement1 = helpers::measurement(60_000_000_000, 1000, 20);
+        let measurement2 = NumaMeasurement {
+            precise_time_ns: 120_000_000_000,
+            nodes: NumaNodes::new(),
+        };
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    mod helpers {
+        use super::{HashMap, NumaMeasurement, NumaNode, NumaNodeMemory, NumaNodes, NumaStat};
+
+        pub fn measurement(precise_time_ns: u64, numa_hit: u64, numa_miss: u64) -> NumaMeasurement {
+            let mut nodes = NumaNodes::new();
+            nodes.insert(
+                "node0".to_owned(),
+                NumaNode {
+                    memory: NumaNodeMemory {
+                        total: 16310548,
+                        free: 2048000,
+                        used: 14262548,
+                        file_pages: None,
+                        anon_pages: None,
+                        shmem: None,
+                        other: HashMap::new(),
+                    },
+                    stat: NumaStat {
+                        numa_hit,
+                        numa_miss,
+                        ..Default::default()
+                    },
+                },
+            );
+            NumaMeasurement {
+                precise_time_ns,
+                nodes,
+            }
+        }
+    }
+}
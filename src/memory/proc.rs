@@ -1,107 +1,202 @@
+use std::collections::HashMap;
+
 use super::Memory;
 use crate::Result;
 
+/// All fields of `/proc/meminfo`. Values are in KB, except for the `huge_pages_*` fields
+/// which are a number of pages.
+#[derive(Debug, Default, PartialEq)]
+pub struct Meminfo {
+    pub mem_total: u64,
+    pub mem_free: Option<u64>,
+    pub mem_available: Option<u64>,
+    pub buffers: Option<u64>,
+    pub cached: Option<u64>,
+    pub swap_cached: Option<u64>,
+    pub active: Option<u64>,
+    pub inactive: Option<u64>,
+    pub active_anon: Option<u64>,
+    pub inactive_anon: Option<u64>,
+    pub active_file: Option<u64>,
+    pub inactive_file: Option<u64>,
+    pub unevictable: Option<u64>,
+    pub mlocked: Option<u64>,
+    pub swap_total: Option<u64>,
+    pub swap_free: Option<u64>,
+    pub dirty: Option<u64>,
+    pub writeback: Option<u64>,
+    pub anon_pages: Option<u64>,
+    pub mapped: Option<u64>,
+    pub shmem: Option<u64>,
+    pub kreclaimable: Option<u64>,
+    pub slab: Option<u64>,
+    pub sreclaimable: Option<u64>,
+    pub sunreclaim: Option<u64>,
+    pub kernel_stack: Option<u64>,
+    pub page_tables: Option<u64>,
+    pub commit_limit: Option<u64>,
+    pub committed_as: Option<u64>,
+    pub vmalloc_total: Option<u64>,
+    pub vmalloc_used: Option<u64>,
+    pub anon_huge_pages: Option<u64>,
+    pub huge_pages_total: Option<u64>,
+    pub huge_pages_free: Option<u64>,
+    pub huge_pages_rsvd: Option<u64>,
+    pub huge_pages_surp: Option<u64>,
+    pub hugepagesize: Option<u64>,
+    pub hugetlb: Option<u64>,
+    /// Fields without a dedicated field in this struct, keyed by their name in `/proc/meminfo`
+    pub other: HashMap<String, u64>,
+}
+
 /// Read the current memory status of the system.
 #[cfg(target_os = "linux")]
 pub fn read() -> Result<Memory> {
     os::read()
 }
 
+/// Read all fields of `/proc/meminfo`.
+#[cfg(target_os = "linux")]
+pub fn read_meminfo() -> Result<Meminfo> {
+    os::read_meminfo()
+}
+
 #[cfg(target_os = "linux")]
 mod os {
     use std::io::BufRead;
     use std::path::Path;
 
-    use super::super::Memory;
+    use super::super::{clamped_sub, Memory};
+    use super::Meminfo;
     use crate::{file_to_buf_reader, parse_u64};
     use crate::{path_to_string, ProbeError, Result};
 
-    const PROC_MEMORY_NUMBER_OF_FIELDS: usize = 7;
-
     #[inline]
     pub fn read() -> Result<Memory> {
-        read_and_parse_proc_memory(&Path::new("/proc/meminfo"))
+        read_and_parse_proc_memory(Path::new("/proc/meminfo"))
     }
 
     #[inline]
-    pub fn read_and_parse_proc_memory(path: &Path) -> Result<Memory> {
-        let mut memory = Memory {
-            total: None,
-            free: None,
-            used: 0,
-            buffers: None,
-            cached: None,
-            shmem: None,
-            swap_total: None,
-            swap_free: None,
-            swap_used: None,
-        };
-        let mut free = 0;
+    pub fn read_meminfo() -> Result<Meminfo> {
+        read_and_parse_proc_meminfo(Path::new("/proc/meminfo"))
+    }
 
-        let reader = file_to_buf_reader(path)?;
+    pub fn read_and_parse_proc_meminfo(path: &Path) -> Result<Meminfo> {
+        let mut meminfo = Meminfo::default();
+        let mut mem_total = None;
 
-        let mut fields_encountered = 0;
+        let reader = file_to_buf_reader(path)?;
         for line_result in reader.lines() {
             let line = line_result.map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
             let segments: Vec<&str> = line.split_whitespace().collect();
+            if segments.len() < 2 {
+                return Err(ProbeError::UnexpectedContent(format!(
+                    "Incorrect number of segments in '{}'",
+                    line
+                )));
+            }
             let value: u64 = parse_u64(segments[1])?;
 
-            // If this is a field we recognize set it's value and increment the
-            // number of fields we encountered.
-            fields_encountered += match segments[0] {
-                "MemTotal:" => {
-                    memory.total = Some(value);
-                    1
-                }
-                "MemFree:" => {
-                    free = value;
-                    1
-                }
-                "Buffers:" => {
-                    memory.buffers = Some(value);
-                    1
-                }
-                "Cached:" => {
-                    memory.cached = Some(value);
-                    1
+            let field = match segments[0].trim_end_matches(':') {
+                "MemTotal" => &mut mem_total,
+                "MemFree" => &mut meminfo.mem_free,
+                "MemAvailable" => &mut meminfo.mem_available,
+                "Buffers" => &mut meminfo.buffers,
+                "Cached" => &mut meminfo.cached,
+                "SwapCached" => &mut meminfo.swap_cached,
+                "Active" => &mut meminfo.active,
+                "Inactive" => &mut meminfo.inactive,
+                "Active(anon)" => &mut meminfo.active_anon,
+                "Inactive(anon)" => &mut meminfo.inactive_anon,
+                "Active(file)" => &mut meminfo.active_file,
+                "Inactive(file)" => &mut meminfo.inactive_file,
+                "Unevictable" => &mut meminfo.unevictable,
+                "Mlocked" => &mut meminfo.mlocked,
+                "SwapTotal" => &mut meminfo.swap_total,
+                "SwapFree" => &mut meminfo.swap_free,
+                "Dirty" => &mut meminfo.dirty,
+                "Writeback" => &mut meminfo.writeback,
+                "AnonPages" => &mut meminfo.anon_pages,
+                "Mapped" => &mut meminfo.mapped,
+                "Shmem" => &mut meminfo.shmem,
+                "KReclaimable" => &mut meminfo.kreclaimable,
+                "Slab" => &mut meminfo.slab,
+                "SReclaimable" => &mut meminfo.sreclaimable,
+                "SUnreclaim" => &mut meminfo.sunreclaim,
+                "KernelStack" => &mut meminfo.kernel_stack,
+                "PageTables" => &mut meminfo.page_tables,
+                "CommitLimit" => &mut meminfo.commit_limit,
+                "Committed_AS" => &mut meminfo.committed_as,
+                "VmallocTotal" => &mut meminfo.vmalloc_total,
+                "VmallocUsed" => &mut meminfo.vmalloc_used,
+                "AnonHugePages" => &mut meminfo.anon_huge_pages,
+                "HugePages_Total" => &mut meminfo.huge_pages_total,
+                "HugePages_Free" => &mut meminfo.huge_pages_free,
+                "HugePages_Rsvd" => &mut meminfo.huge_pages_rsvd,
+                "HugePages_Surp" => &mut meminfo.huge_pages_surp,
+                "Hugepagesize" => &mut meminfo.hugepagesize,
+                "Hugetlb" => &mut meminfo.hugetlb,
+                key => {
+                    meminfo.other.insert(key.to_owned(), value);
+                    continue;
                 }
-                "SwapTotal:" => {
-                    memory.swap_total = Some(value);
-                    1
-                }
-                "SwapFree:" => {
-                    memory.swap_free = Some(value);
-                    1
-                }
-                "Shmem:" => {
-                    memory.shmem = Some(value);
-                    1
-                }
-                _ => 0,
             };
+            *field = Some(value);
+        }
+
+        meminfo.mem_total = mem_total.ok_or_else(|| {
+            ProbeError::UnexpectedContent("Did not encounter all expected fields".to_owned())
+        })?;
+
+        Ok(meminfo)
+    }
+
+    #[inline]
+    pub fn read_and_parse_proc_memory(path: &Path) -> Result<Memory> {
+        let meminfo = read_and_parse_proc_meminfo(path)?;
 
-            if fields_encountered == PROC_MEMORY_NUMBER_OF_FIELDS {
-                break;
+        let free = match (
+            meminfo.mem_free,
+            meminfo.buffers,
+            meminfo.cached,
+            meminfo.swap_total,
+            meminfo.swap_free,
+            meminfo.shmem,
+        ) {
+            (Some(free), Some(_), Some(_), Some(_), Some(_), Some(_)) => free,
+            _ => {
+                return Err(ProbeError::UnexpectedContent(
+                    "Did not encounter all expected fields".to_owned(),
+                ))
             }
-        }
+        };
 
-        if fields_encountered != PROC_MEMORY_NUMBER_OF_FIELDS || memory.total.is_none() {
-            return Err(ProbeError::UnexpectedContent(
-                "Did not encounter all expected fields".to_owned(),
-            ));
-        }
+        // Total amount of free physical memory in Kb. Use the kernel's estimate of the memory
+        // available for new allocations when present. On older kernels this includes buffers
+        // and caches, these will be freed up by the OS when the memory is needed.
+        let free = meminfo
+            .mem_available
+            .unwrap_or_else(|| free + meminfo.buffers.unwrap_or(0) + meminfo.cached.unwrap_or(0));
+
+        let mut inconsistent = false;
 
-        // Total amount of free physical memory in Kb.
-        // Includes buffers and caches, these will be freed
-        // up by the OS when the memory is needed.
-        memory.free = Some(free + memory.buffers.unwrap_or(0) + memory.cached.unwrap_or(0));
-        memory.used = memory.total.unwrap() - memory.free.unwrap();
-        memory.swap_used = memory
-            .swap_total
-            .zip(memory.swap_free)
-            .map(|(total, free)| total - free);
-
-        Ok(memory)
+        Ok(Memory {
+            total: Some(meminfo.mem_total),
+            free: Some(free),
+            used: clamped_sub(meminfo.mem_total, free, &mut inconsistent),
+            buffers: meminfo.buffers,
+            cached: meminfo.cached,
+            shmem: meminfo.shmem,
+            swap_total: meminfo.swap_total,
+            swap_free: meminfo.swap_free,
+            swap_used: meminfo
+                .swap_total
+                .zip(meminfo.swap_free)
+                .map(|(total, free)| clamped_sub(total, free, &mut inconsistent)),
+            rss: None,
+            working_set: None,
+            inconsistent,
+        })
     }
 }
 
@@ -120,7 +215,7 @@ mod tests {
     #[test]
     fn test_read_and_parse_proc_memory() {
         let path = Path::new("fixtures/linux/memory/proc_meminfo");
-        let memory = super::os::read_and_parse_proc_memory(&path).unwrap();
+        let memory = super::os::read_and_parse_proc_memory(path).unwrap();
 
         let expected = Memory {
             total: Some(376072),
@@ -132,6 +227,9 @@ mod tests {
             swap_total: Some(1101816),
             swap_free: Some(1100644),
             swap_used: Some(1172),
+            rss: None,
+            working_set: None,
+            inconsistent: false,
         };
         assert_eq!(expected, memory);
         assert_eq!(memory.total.unwrap(), memory.used + memory.free.unwrap());
@@ -141,10 +239,99 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_read_and_parse_proc_memory_with_mem_available() {
+        let path = Path::new("fixtures/linux/memory/proc_meminfo_available");
+        let memory = super::os::read_and_parse_proc_memory(path).unwrap();
+
+        let expected = Memory {
+            total: Some(6147400),
+            free: Some(5594680), // MemAvailable
+            used: 552720,
+            buffers: Some(72652),
+            cached: Some(1859112),
+            shmem: Some(9288),
+            swap_total: Some(2097148),
+            swap_free: Some(2000000),
+            swap_used: Some(97148),
+            rss: None,
+            working_set: None,
+            inconsistent: false,
+        };
+        assert_eq!(expected, memory);
+        assert_eq!(memory.total.unwrap(), memory.used + memory.free.unwrap());
+    }
+
+    #[test]
+    fn test_read_meminfo() {
+        assert!(super::read_meminfo().unwrap().mem_total > 0);
+    }
+
+    #[test]
+    fn test_read_and_parse_proc_meminfo() {
+        let path = Path::new("fixtures/linux/memory/proc_meminfo_available");
+        let meminfo = super::os::read_and_parse_proc_meminfo(path).unwrap();
+
+        assert_eq!(6147400, meminfo.mem_total);
+        assert_eq!(Some(3824788), meminfo.mem_free);
+        assert_eq!(Some(5594680), meminfo.mem_available);
+        assert_eq!(Some(1333336), meminfo.inactive_file);
+        assert_eq!(Some(43700), meminfo.dirty);
+        assert_eq!(Some(184352), meminfo.anon_pages);
+        assert_eq!(Some(143752), meminfo.mapped);
+        assert_eq!(Some(142156), meminfo.slab);
+        assert_eq!(Some(117432), meminfo.sreclaimable);
+        assert_eq!(Some(24724), meminfo.sunreclaim);
+        assert_eq!(Some(2036), meminfo.page_tables);
+        assert_eq!(Some(3073700), meminfo.commit_limit);
+        assert_eq!(Some(338096), meminfo.committed_as);
+        assert_eq!(Some(512), meminfo.huge_pages_total);
+        assert_eq!(Some(256), meminfo.huge_pages_free);
+        assert_eq!(Some(16), meminfo.huge_pages_rsvd);
+        assert_eq!(Some(0), meminfo.huge_pages_surp);
+        assert_eq!(Some(2048), meminfo.hugepagesize);
+        assert_eq!(Some(1048576), meminfo.hugetlb);
+
+        // Fields without a dedicated field
+        assert_eq!(Some(&591872), meminfo.other.get("FileHugePages"));
+        assert_eq!(Some(&320), meminfo.other.get("Percpu"));
+        assert_eq!(Some(&6291456), meminfo.other.get("DirectMap1G"));
+        assert_eq!(None, meminfo.other.get("MemTotal"));
+    }
+
+    #[test]
+    fn test_read_and_parse_proc_meminfo_old_kernel() {
+        let path = Path::new("fixtures/linux/memory/proc_meminfo");
+        let meminfo = super::os::read_and_parse_proc_meminfo(path).unwrap();
+
+        assert_eq!(376072, meminfo.mem_total);
+        assert_eq!(None, meminfo.mem_available);
+        assert_eq!(None, meminfo.kreclaimable);
+        assert_eq!(Some(0), meminfo.huge_pages_total);
+    }
+
+    #[test]
+    fn test_read_and_parse_proc_meminfo_missing_total() {
+        let path = Path::new("fixtures/linux/memory/proc_meminfo_missing_total");
+        match super::os::read_and_parse_proc_meminfo(path) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_proc_meminfo_garbage() {
+        let path = Path::new("fixtures/linux/memory/proc_meminfo_garbage");
+        match super::os::read_and_parse_proc_meminfo(path) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
     #[test]
     fn test_read_and_parse_memory_wrong_path() {
         let path = Path::new("/nonsense");
-        match super::os::read_and_parse_proc_memory(&path) {
+        match super::os::read_and_parse_proc_memory(path) {
             Err(ProbeError::IO(_, _)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -153,7 +340,7 @@ mod tests {
     #[test]
     fn test_read_and_parse_memory_incomplete() {
         let path = Path::new("fixtures/linux/memory/proc_meminfo_incomplete");
-        match super::os::read_and_parse_proc_memory(&path) {
+        match super::os::read_and_parse_proc_memory(path) {
             Err(ProbeError::UnexpectedContent(_)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -162,7 +349,7 @@ mod tests {
     #[test]
     fn test_read_and_parse_memory_garbage() {
         let path = Path::new("fixtures/linux/memory/proc_meminfo_garbage");
-        match super::os::read_and_parse_proc_memory(&path) {
+        match super::os::read_and_parse_proc_memory(path) {
             Err(ProbeError::UnexpectedContent(_)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+
+use super::disk_stats::{DiskIoStat, DiskIoStats};
+use super::disk_usage::DiskUsage;
+use super::Result;
+
+/// Names of the devices mounted filesystems are on, as used in `/proc/diskstats`, by mountpoint
+pub type MountDevices = HashMap<String, String>;
+
+/// Disk space usage of a mounted filesystem together with the I/O of the device it's on
+#[derive(Debug, PartialEq)]
+pub struct MountpointStats {
+    pub mountpoint: String,
+    /// Name of the device as used in `/proc/diskstats`, `None` when it could not be found
+    pub device: Option<String>,
+    pub usage: DiskUsage,
+    /// I/O of the device, `None` when it could not be found
+    pub io: Option<DiskIoStat>,
+}
+
+/// Read which device every mounted filesystem is on. The device is found by the major and minor
+/// number of the mount, or by resolving its source such as `/dev/mapper/vg0-root` or
+/// `/dev/disk/by-uuid/<uuid>` for filesystems like btrfs which report a virtual device number.
+#[cfg(target_os = "linux")]
+pub fn read_mount_devices() -> Result<MountDevices> {
+    os::read_mount_devices()
+}
+
+/// Combine the disk usage from `disk_usage::read` with the I/O stats from
+/// `DiskStatsMeasurement::calculate_io_stats` per mountpoint.
+pub fn join(
+    usage: Vec<DiskUsage>,
+    io_stats: &DiskIoStats,
+    devices: &MountDevices,
+) -> Vec<MountpointStats> {
+    usage
+        .into_iter()
+        .map(|usage| {
+            let device = devices.get(&usage.mountpoint).cloned();
+            let io = device
+                .as_ref()
+                .and_then(|device| io_stats.get(device))
+                .cloned();
+
+            MountpointStats {
+                mountpoint: usage.mountpoint.to_owned(),
+                device,
+                usage,
+                io,
+            }
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+mod os {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::Path;
+
+    use super::super::disk_stats;
+    use super::super::mounts::{self, Mount};
+    use super::super::Result;
+    use super::MountDevices;
+
+    #[inline]
+    pub fn read_mount_devices() -> Result<MountDevices> {
+        let mounts = mounts::read()?;
+        let device_names = disk_stats::read()?.device_names();
+        Ok(mount_devices(&mounts, &device_names, Path::new("/dev")))
+    }
+
+    /// Find the device of every mount, a later mount on the same mountpoint hides earlier ones
+    pub fn mount_devices(
+        mounts: &[Mount],
+        device_names: &HashMap<(u64, u64), String>,
+        dev_path: &Path,
+    ) -> MountDevices {
+        let mut out = HashMap::new();
+        for mount in mounts {
+            let device = device_names
+                .get(&(mount.major, mount.minor))
+                .cloned()
+                .or_else(|| resolve_source(&mount.source, dev_path))
+                .filter(|device| device_names.values().any(|name| name == device));
+
+            match device {
+                Some(device) => out.insert(mount.mountpoint.to_owned(), device),
+                None => out.remove(&mount.mountpoint),
+            };
+        }
+
+        out
+    }
+
+    /// Follow the symlinks of a source in `/dev`, such as `/dev/mapper/vg0-root` to `/dev/dm-0`
+    #[inline]
+    fn resolve_source(source: &str, dev_path: &Path) -> Option<String> {
+        let relative_source = source.strip_prefix("/dev/")?;
+        let resolved = fs::canonicalize(dev_path.join(relative_source)).ok()?;
+        resolved
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name.to_owned())
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::super::disk_stats;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_read_mount_devices() {
+        let devices = super::read_mount_devices().unwrap();
+        let measurement = disk_stats::read().unwrap();
+
+        // Every device found is in /proc/diskstats
+        for device in devices.values() {
+            assert!(measurement.stats.contains_key(device));
+        }
+    }
+
+    #[test]
+    fn test_mount_devices() {
+        let devices = helpers::mount_devices();
+
+        let mut expected = HashMap::new();
+        // By major and minor number
+        expected.insert("/".to_owned(), "dm-0".to_owned());
+        expected.insert("/boot".to_owned(), "sda1".to_owned());
+        // By resolving the source
+        expected.insert("/home".to_owned(), "dm-1".to_owned());
+        expected.insert("/data".to_owned(), "sdb".to_owned());
+        assert_eq!(expected, devices);
+    }
+
+    #[test]
+    fn test_join() {
+        let usage = vec![
+            helpers::disk_usage("/"),
+            helpers::disk_usage("/data"),
+            helpers::disk_usage("/mnt/lost"),
+        ];
+        let mut io_stats = HashMap::new();
+        io_stats.insert("dm-0".to_owned(), helpers::io_stat(10.0));
+        io_stats.insert("sdb".to_owned(), helpers::io_stat(20.0));
+
+        let stats = super::join(usage, &io_stats, &helpers::mount_devices());
+
+        assert_eq!(3, stats.len());
+        assert_eq!("/", stats[0].mountpoint);
+        assert_eq!(Some("dm-0".to_owned()), stats[0].device);
+        assert_eq!(helpers::disk_usage("/"), stats[0].usage);
+        assert_eq!(Some(helpers::io_stat(10.0)), stats[0].io);
+        assert_eq!(Some("sdb".to_owned()), stats[1].device);
+        assert_eq!(Some(helpers::io_stat(20.0)), stats[1].io);
+        assert_eq!(None, stats[2].device);
+        assert_eq!(None, stats[2].io);
+    }
+
+    mod helpers {
+        use super::super::super::disk_stats::{self, DiskIoStat};
+        use super::super::super::disk_usage::DiskUsage;
+        use super::super::super::mounts;
+        use super::super::MountDevices;
+        use std::path::Path;
+
+        pub fn mount_devices() -> MountDevices {
+            let mounts = mounts::os::read_and_parse_mountinfo(Path::new(
+                "fixtures/linux/mountpoint_stats/mountinfo",
+            ))
+            .unwrap();
+            let device_names = disk_stats::os::read_and_parse_proc_diskstats(Path::new(
+                "fixtures/linux/mountpoint_stats/proc_diskstats",
+            ))
+            .unwrap()
+            .device_names();
+
+            super::super::os::mount_devices(
+                &mounts,
+                &device_names,
+                Path::new("fixtures/linux/mountpoint_stats/dev"),
+            )
+        }
+
+        pub fn disk_usage(mountpoint: &str) -> DiskUsage {
+            DiskUsage {
+                filesystem: Some("/dev/sda1".to_owned()),
+                one_k_blocks: 4000,
+                one_k_blocks_used: 2400,
+                one_k_blocks_free: 1400,
+                used_percentage: 64,
+                mountpoint: mountpoint.to_owned(),
+                bytes: Some(4_096_000),
+                bytes_used: Some(2_457_600),
+                bytes_free: Some(1_433_600),
+                fs_type: Some("ext4".to_owned()),
+                read_only: Some(false),
+            }
+        }
+
+        pub fn io_stat(value: f32) -> DiskIoStat {
+            DiskIoStat {
+                reads_per_second: value,
+                writes_per_second: value,
+                read_bytes_per_second: value,
+                write_bytes_per_second: value,
+                average_read_request_size: value,
+                average_write_request_size: value,
+                read_await_ms: value,
+                write_await_ms: value,
+                average_queue_size: value,
+                utilization_percentage: value,
+            }
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use super::Result;
+
+/// A mounted filesystem from `/proc/self/mountinfo`
+#[derive(Debug, PartialEq)]
+pub struct Mount {
+    pub mount_id: u64,
+    /// Id of the mount this filesystem is mounted on, or of itself for the root of the tree
+    pub parent_id: u64,
+    /// Major number of the device backing the filesystem
+    pub major: u64,
+    /// Minor number of the device backing the filesystem
+    pub minor: u64,
+    /// Directory within the filesystem which is mounted, which differs from `/` for bind mounts
+    pub root: String,
+    pub mountpoint: String,
+    /// Per mount options, such as `ro` and `noexec`
+    pub mount_options: Vec<String>,
+    pub fs_type: String,
+    /// Device or other source of the filesystem, such as `/dev/sda1` or `tmpfs`
+    pub source: String,
+    /// Options of the filesystem itself, shared by all its mounts
+    pub super_options: Vec<String>,
+}
+
+impl Mount {
+    pub fn is_read_only(&self) -> bool {
+        self.mount_options.iter().any(|option| option == "ro")
+    }
+}
+
+/// Read all filesystems mounted in the mount namespace of this process.
+#[cfg(target_os = "linux")]
+pub fn read() -> Result<Vec<Mount>> {
+    os::read()
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) mod os {
+    use std::io::BufRead;
+    use std::path::Path;
+
+    use super::super::{
+        file_to_buf_reader, parse_u64, path_to_string, unescape_octal, ProbeError, Result,
+    };
+    use super::Mount;
+
+    #[inline]
+    pub fn read() -> Result<Vec<Mount>> {
+        read_and_parse_mountinfo(Path::new("/proc/self/mountinfo"))
+    }
+
+    pub fn read_and_parse_mountinfo(path: &Path) -> Result<Vec<Mount>> {
+        let reader = file_to_buf_reader(path)?;
+
+        let mut out = Vec::new();
+        for line_result in reader.lines() {
+            let line = line_result.map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
+            out.push(parse_mountinfo_line(&line)?);
+        }
+
+        Ok(out)
+    }
+
+    fn parse_mountinfo_line(line: &str) -> Result<Mount> {
+        let segments: Vec<&str> = line.split_whitespace().collect();
+
+        // The optional fields are terminated by a single dash, followed by the filesystem
+        // type, the mount source and the super block options.
+        let fields_after = segments
+            .iter()
+            .skip(6)
+            .position(|segment| *segment == "-")
+            .map(|position| &segments[position + 7..]);
+        let fields_after = match fields_after {
+            Some(fields_after) if fields_after.len() >= 2 => fields_after,
+            _ => {
+                return Err(ProbeError::UnexpectedContent(format!(
+                    "Incorrect number of segments in '{}'",
+                    line
+                )))
+            }
+        };
+
+        let mut device = segments[2].splitn(2, ':');
+        let (major, minor) = match (device.next(), device.next()) {
+            (Some(major), Some(minor)) => (parse_u64(major)?, parse_u64(minor)?),
+            _ => {
+                return Err(ProbeError::UnexpectedContent(format!(
+                    "Could not parse device '{}'",
+                    segments[2]
+                )))
+            }
+        };
+
+        Ok(Mount {
+            mount_id: parse_u64(segments[0])?,
+            parent_id: parse_u64(segments[1])?,
+            major,
+            minor,
+            root: unescape_octal(segments[3]),
+            mountpoint: unescape_octal(segments[4]),
+            mount_options: parse_options(segments[5]),
+            fs_type: fields_after[0].to_owned(),
+            source: unescape_octal(fields_after[1]),
+            super_options: fields_after
+                .get(2)
+                .map_or_else(Vec::new, |o| parse_options(o)),
+        })
+    }
+
+    #[inline]
+    fn parse_options(segment: &str) -> Vec<String> {
+        segment.split(',').map(|option| option.to_owned()).collect()
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::super::ProbeError;
+    use super::Mount;
+    use std::path::Path;
+
+    #[test]
+    fn test_read_mounts() {
+        let mounts = super::read().unwrap();
+        assert!(mounts.iter().any(|mount| mount.mountpoint == "/"));
+    }
+
+    #[test]
+    fn test_read_and_parse_mountinfo() {
+        let path = Path::new("fixtures/linux/mounts/mountinfo");
+        let mounts = super::os::read_and_parse_mountinfo(path).unwrap();
+
+        assert_eq!(14, mounts.len());
+        assert_eq!(
+            Mount {
+                mount_id: 29,
+                parent_id: 22,
+                major: 8,
+                minor: 1,
+                root: "/var/lib/docker/containers/abc/resolv.conf".to_owned(),
+                mountpoint: "/etc/resolv.conf".to_owned(),
+                mount_options: vec!["rw".to_owned(), "relatime".to_owned()],
+                fs_type: "ext4".to_owned(),
+                source: "/dev/sda1".to_owned(),
+                super_options: vec!["rw".to_owned()],
+            },
+            mounts[7]
+        );
+        // Optional fields such as `shared:5` are skipped and escaped spaces are decoded
+        assert_eq!(
+            Mount {
+                mount_id: 32,
+                parent_id: 22,
+                major: 8,
+                minor: 17,
+                root: "/".to_owned(),
+                mountpoint: "/mnt/my data".to_owned(),
+                mount_options: vec!["ro".to_owned(), "relatime".to_owned()],
+                fs_type: "xfs".to_owned(),
+                source: "/dev/sdb1".to_owned(),
+                super_options: vec!["rw".to_owned(), "attr2".to_owned(), "inode64".to_owned()],
+            },
+            mounts[10]
+        );
+
+        assert!(!mounts[0].is_read_only());
+        assert!(mounts[4].is_read_only());
+        assert!(mounts[10].is_read_only());
+    }
+
+    #[test]
+    fn test_read_and_parse_mountinfo_wrong_path() {
+        match super::os::read_and_parse_mountinfo(Path::new("/nonsense")) {
+            Err(ProbeError::IO(_, _)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_mountinfo_incomplete() {
+        let path = Path::new("fixtures/linux/mounts/mountinfo_incomplete");
+        match super::os::read_and_parse_mountinfo(path) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_mountinfo_garbage() {
+        let path = Path::new("fixtures/linux/mounts/mountinfo_garbage");
+        match super::os::read_and_parse_mountinfo(path) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+}
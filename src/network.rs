@@ -1,4 +1,4 @@
-use super::{calculate_time_difference, ProbeError, Result};
+use super::{calculate_time_difference, FieldKind, ProbeError, Result};
 use std::collections::HashMap;
 
 pub type Interfaces = HashMap<String, NetworkTraffic>;
@@ -36,14 +36,16 @@ impl NetworkTrafficMeasurement {
             interfaces.insert(
                 name.to_string(),
                 NetworkTraffic {
-                    received: super::time_adjusted(
+                    received: super::per_minute_value(
                         "received",
+                        FieldKind::Counter,
                         next_traffic.received,
                         traffic.received,
                         time_difference,
                     )?,
-                    transmitted: super::time_adjusted(
+                    transmitted: super::per_minute_value(
                         "transmitted",
+                        FieldKind::Counter,
                         next_traffic.transmitted,
                         traffic.transmitted,
                         time_difference,
@@ -85,7 +87,7 @@ mod os {
 
     #[inline]
     pub fn read() -> Result<NetworkTrafficMeasurement> {
-        read_and_parse_network(&Path::new("/proc/net/dev"))
+        read_and_parse_network(Path::new("/proc/net/dev"))
     }
 
     #[inline]
@@ -181,7 +183,7 @@ mod tests {
     #[test]
     fn test_read_and_parse_network() {
         let path = Path::new("fixtures/linux/network/proc_net_dev");
-        let measurement = super::os::read_and_parse_network(&path).unwrap();
+        let measurement = super::os::read_and_parse_network(path).unwrap();
 
         assert!(measurement.precise_time_ns < precise_time_ns());
 
@@ -204,7 +206,7 @@ mod tests {
     #[test]
     fn test_read_and_parse_network_wrong_path() {
         let path = Path::new("/nonsense");
-        match super::os::read_and_parse_network(&path) {
+        match super::os::read_and_parse_network(path) {
             Err(ProbeError::IO(_, _)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -213,7 +215,7 @@ mod tests {
     #[test]
     fn test_read_and_parse_network_incomplete() {
         let path = Path::new("fixtures/linux/network/proc_net_dev_incomplete");
-        match super::os::read_and_parse_network(&path) {
+        match super::os::read_and_parse_network(path) {
             Err(ProbeError::UnexpectedContent(_)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -222,7 +224,7 @@ mod tests {
     #[test]
     fn test_read_and_parse_network_garbage() {
         let path = Path::new("fixtures/linux/network/proc_net_dev_garbage");
-        match super::os::read_and_parse_network(&path) {
+        match super::os::read_and_parse_network(path) {
             Err(ProbeError::UnexpectedContent(_)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
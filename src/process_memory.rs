@@ -31,12 +31,12 @@ mod os {
 
     #[inline]
     pub fn current_rss() -> Result<u64> {
-        read_and_get_current_rss(&Path::new("/proc/self/statm"))
+        read_and_get_current_rss(Path::new("/proc/self/statm"))
     }
 
     #[inline]
     pub fn current_rss_of(pid: libc::pid_t) -> Result<u64> {
-        read_and_get_current_rss(&Path::new(&format!("/proc/{}/statm", pid)))
+        read_and_get_current_rss(Path::new(&format!("/proc/{}/statm", pid)))
     }
 
     #[inline]
@@ -89,14 +89,14 @@ mod tests {
     #[test]
     fn test_read_and_get_current_rss() {
         let path = Path::new("fixtures/linux/process_memory/proc_self_statm");
-        let value = super::os::read_and_get_current_rss(&path).unwrap();
+        let value = super::os::read_and_get_current_rss(path).unwrap();
         assert_eq!(4552, value);
     }
 
     #[test]
     fn test_read_and_get_current_rss_wrong_path() {
         let path = Path::new("/nonsense");
-        match super::os::read_and_get_current_rss(&path) {
+        match super::os::read_and_get_current_rss(path) {
             Err(ProbeError::IO(_, _)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -105,7 +105,7 @@ mod tests {
     #[test]
     fn test_read_and_get_current_rss_incomplete() {
         let path = Path::new("fixtures/linux/process_memory/proc_self_statm_incomplete");
-        match super::os::read_and_get_current_rss(&path) {
+        match super::os::read_and_get_current_rss(path) {
             Err(ProbeError::UnexpectedContent(_)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
@@ -114,7 +114,7 @@ mod tests {
     #[test]
     fn test_read_and_get_current_rss_garbage() {
         let path = Path::new("fixtures/linux/process_memory/proc_self_statm_garbage");
-        match super::os::read_and_get_current_rss(&path) {
+        match super::os::read_and_get_current_rss(path) {
             Err(ProbeError::UnexpectedContent(_)) => (),
             r => panic!("Unexpected result: {:?}", r),
         }
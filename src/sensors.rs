@@ -0,0 +1,303 @@
+use super::Result;
+
+/// Temperature of a thermal zone, such as `x86_pkg_temp` or `acpitz`.
+#[derive(Debug, PartialEq)]
+pub struct ThermalZone {
+    pub name: String,
+    pub zone_type: String,
+    /// Temperature in degrees Celsius
+    pub temperature: f64,
+}
+
+/// A hardware monitoring chip and the readings of its sensors.
+#[derive(Debug, PartialEq)]
+pub struct HwmonDevice {
+    pub name: String,
+    pub chip: Option<String>,
+    pub readings: Vec<SensorReading>,
+}
+
+/// A single sensor of a hardware monitoring chip, such as `temp1` or `fan2`.
+#[derive(Debug, PartialEq)]
+pub struct SensorReading {
+    pub sensor: String,
+    pub label: Option<String>,
+    pub value: SensorValue,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SensorValue {
+    /// Temperature in degrees Celsius
+    Temperature(f64),
+    /// Fan speed in revolutions per minute
+    Fan(u64),
+    /// Voltage in volts
+    Voltage(f64),
+}
+
+/// Read the current temperature of all thermal zones.
+#[cfg(target_os = "linux")]
+pub fn read_thermal_zones() -> Result<Vec<ThermalZone>> {
+    os::read_thermal_zones()
+}
+
+/// Read the current temperature, fan and voltage readings of all hardware monitoring chips.
+#[cfg(target_os = "linux")]
+pub fn read_hwmon() -> Result<Vec<HwmonDevice>> {
+    os::read_hwmon()
+}
+
+#[cfg(target_os = "linux")]
+mod os {
+    use std::fs;
+    use std::path::Path;
+
+    use super::{HwmonDevice, SensorReading, SensorValue, ThermalZone};
+    use crate::{
+        dir_exists, file_to_string, numbered_dir_entries, parse_i64, parse_u64, path_to_string,
+        ProbeError, Result,
+    };
+
+    #[inline]
+    pub fn read_thermal_zones() -> Result<Vec<ThermalZone>> {
+        read_and_parse_thermal_zones(Path::new("/sys/class/thermal"))
+    }
+
+    #[inline]
+    pub fn read_hwmon() -> Result<Vec<HwmonDevice>> {
+        read_and_parse_hwmon(Path::new("/sys/class/hwmon"))
+    }
+
+    pub fn read_and_parse_thermal_zones(path: &Path) -> Result<Vec<ThermalZone>> {
+        // Systems without thermal sensors, such as most VMs, have no thermal class at all
+        if !dir_exists(path) {
+            return Ok(Vec::new());
+        }
+
+        let mut out = Vec::new();
+        for (_, zone_path) in numbered_dir_entries(path, "thermal_zone")? {
+            // Reading the temperature fails for zones of disabled devices
+            let temperature = match file_to_string(&zone_path.join("temp")) {
+                Ok(raw) => parse_i64(raw.trim())? as f64 / 1000.0,
+                Err(ProbeError::IO(_, _)) => continue,
+                Err(e) => return Err(e),
+            };
+
+            out.push(ThermalZone {
+                name: file_name(&zone_path),
+                zone_type: file_to_string(&zone_path.join("type"))?.trim().to_owned(),
+                temperature,
+            });
+        }
+
+        Ok(out)
+    }
+
+    pub fn read_and_parse_hwmon(path: &Path) -> Result<Vec<HwmonDevice>> {
+        if !dir_exists(path) {
+            return Ok(Vec::new());
+        }
+
+        let mut out = Vec::new();
+        for (_, device_path) in numbered_dir_entries(path, "hwmon")? {
+            let chip = file_to_string(&device_path.join("name"))
+                .ok()
+                .map(|name| name.trim().to_owned());
+
+            let mut inputs = Vec::new();
+            let entries = fs::read_dir(&device_path)
+                .map_err(|e| ProbeError::IO(e, path_to_string(&device_path)))?;
+            for entry in entries {
+                let entry = entry.map_err(|e| ProbeError::IO(e, path_to_string(&device_path)))?;
+                if let Some(sensor) = entry
+                    .file_name()
+                    .to_str()
+                    .and_then(|name| name.strip_suffix("_input"))
+                {
+                    inputs.push(sensor.to_owned());
+                }
+            }
+            inputs.sort_by(|a, b| sensor_sort_key(a).cmp(&sensor_sort_key(b)));
+
+            let mut readings = Vec::new();
+            for sensor in inputs {
+                // Many drivers fail to read channels that are not connected or not active
+                let raw = match file_to_string(&device_path.join(format!("{}_input", sensor))) {
+                    Ok(raw) => raw,
+                    Err(ProbeError::IO(_, _)) => continue,
+                    Err(e) => return Err(e),
+                };
+                let raw = raw.trim();
+                let value = if sensor.starts_with("temp") {
+                    SensorValue::Temperature(parse_i64(raw)? as f64 / 1000.0)
+                } else if sensor.starts_with("fan") {
+                    SensorValue::Fan(parse_u64(raw)?)
+                } else if sensor.starts_with("in") {
+                    SensorValue::Voltage(parse_i64(raw)? as f64 / 1000.0)
+                } else {
+                    // Other sensor types, such as power and current, are not supported
+                    continue;
+                };
+
+                let label = file_to_string(&device_path.join(format!("{}_label", sensor)))
+                    .ok()
+                    .map(|label| label.trim().to_owned());
+
+                readings.push(SensorReading {
+                    sensor,
+                    label,
+                    value,
+                });
+            }
+
+            out.push(HwmonDevice {
+                name: file_name(&device_path),
+                chip,
+                readings,
+            });
+        }
+
+        Ok(out)
+    }
+
+    /// Sort sensors by type and then by number, so `temp2` comes before `temp10`
+    #[inline]
+    fn sensor_sort_key(sensor: &str) -> (&str, u64) {
+        let number_start = sensor
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(sensor.len());
+        let (sensor_type, number) = sensor.split_at(number_start);
+        (sensor_type, number.parse().unwrap_or(0))
+    }
+
+    #[inline]
+    fn file_name(path: &Path) -> String {
+        path.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::os::{read_and_parse_hwmon, read_and_parse_thermal_zones};
+    use super::{HwmonDevice, SensorReading, SensorValue, ThermalZone};
+    use crate::ProbeError;
+    use std::path::Path;
+
+    #[test]
+    fn test_read_thermal_zones() {
+        assert!(super::read_thermal_zones().is_ok());
+    }
+
+    #[test]
+    fn test_read_hwmon() {
+        assert!(super::read_hwmon().is_ok());
+    }
+
+    #[test]
+    fn test_read_and_parse_thermal_zones() {
+        let zones =
+            read_and_parse_thermal_zones(Path::new("fixtures/linux/sys/class/thermal")).unwrap();
+
+        let expected = vec![
+            ThermalZone {
+                name: "thermal_zone0".to_owned(),
+                zone_type: "x86_pkg_temp".to_owned(),
+                temperature: 54.0,
+            },
+            ThermalZone {
+                name: "thermal_zone1".to_owned(),
+                zone_type: "acpitz".to_owned(),
+                temperature: -2.5,
+            },
+        ];
+        assert_eq!(expected, zones);
+    }
+
+    #[test]
+    fn test_read_and_parse_thermal_zones_missing_dir() {
+        let zones = read_and_parse_thermal_zones(Path::new("/nonsense")).unwrap();
+        assert!(zones.is_empty());
+    }
+
+    #[test]
+    fn test_read_and_parse_thermal_zones_garbage() {
+        match read_and_parse_thermal_zones(Path::new("fixtures/linux/sys/class/thermal_garbage")) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_hwmon() {
+        let devices = read_and_parse_hwmon(Path::new("fixtures/linux/sys/class/hwmon")).unwrap();
+
+        let expected = vec![
+            HwmonDevice {
+                name: "hwmon0".to_owned(),
+                chip: Some("coretemp".to_owned()),
+                readings: vec![
+                    SensorReading {
+                        sensor: "temp1".to_owned(),
+                        label: Some("Package id 0".to_owned()),
+                        value: SensorValue::Temperature(55.0),
+                    },
+                    SensorReading {
+                        sensor: "temp2".to_owned(),
+                        label: Some("Core 0".to_owned()),
+                        value: SensorValue::Temperature(52.0),
+                    },
+                    // temp3 can't be read and is skipped
+                    SensorReading {
+                        sensor: "temp10".to_owned(),
+                        label: Some("Core 8".to_owned()),
+                        value: SensorValue::Temperature(48.0),
+                    },
+                ],
+            },
+            HwmonDevice {
+                name: "hwmon1".to_owned(),
+                chip: Some("nct6775".to_owned()),
+                readings: vec![
+                    SensorReading {
+                        sensor: "fan1".to_owned(),
+                        label: Some("CPU Fan".to_owned()),
+                        value: SensorValue::Fan(1250),
+                    },
+                    SensorReading {
+                        sensor: "fan2".to_owned(),
+                        label: None,
+                        value: SensorValue::Fan(0),
+                    },
+                    SensorReading {
+                        sensor: "in0".to_owned(),
+                        label: Some("Vcore".to_owned()),
+                        value: SensorValue::Voltage(1.104),
+                    },
+                    SensorReading {
+                        sensor: "in1".to_owned(),
+                        label: None,
+                        value: SensorValue::Voltage(3.312),
+                    },
+                ],
+            },
+        ];
+        assert_eq!(expected, devices);
+    }
+
+    #[test]
+    fn test_read_and_parse_hwmon_missing_dir() {
+        let devices = read_and_parse_hwmon(Path::new("/nonsense")).unwrap();
+        assert!(devices.is_empty());
+    }
+
+    #[test]
+    fn test_read_and_parse_hwmon_garbage() {
+        match read_and_parse_hwmon(Path::new("fixtures/linux/sys/class/hwmon_garbage")) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use super::{calculate_time_difference, per_minute_counters_per_cpu, Result};
+use std::collections::HashMap;
+
+pub type Softirqs = HashMap<String, Softirq>;
+
+/// Measurement of softirq counts at a certain time.
+#[derive(Debug, PartialEq)]
+pub struct SoftirqsMeasurement {
+    pub precise_time_ns: u64,
+    pub softirqs: Softirqs,
+}
+
+impl SoftirqsMeasurement {
+    /// Calculate the softirqs per minute based on this measurement and a measurement in the
+    /// future. It is advisable to make the next measurement roughly a minute from this one for the
+    /// most reliable result.
+    pub fn calculate_per_minute(
+        &self,
+        next_measurement: &SoftirqsMeasurement,
+    ) -> Result<SoftirqsPerMinute> {
+        let time_difference =
+            calculate_time_difference(self.precise_time_ns, next_measurement.precise_time_ns)?;
+
+        let softirqs = per_minute_counters_per_cpu(
+            &next_measurement.softirqs,
+            &self.softirqs,
+            time_difference,
+            |softirq| &softirq.per_cpu,
+            |_, per_cpu| Softirq { per_cpu },
+        )?;
+
+        Ok(SoftirqsPerMinute { softirqs })
+    }
+}
+
+/// Softirq counts of a single softirq type, such as `NET_RX`, per CPU.
+#[derive(Debug, PartialEq)]
+pub struct Softirq {
+    pub per_cpu: Vec<u64>,
+}
+
+impl Softirq {
+    pub fn total(&self) -> u64 {
+        self.per_cpu.iter().sum()
+    }
+}
+
+/// Softirqs for a certain minute, calculated based on two measurements.
+#[derive(Debug, PartialEq)]
+pub struct SoftirqsPerMinute {
+    pub softirqs: Softirqs,
+}
+
+/// Read the current softirq counts of the system.
+#[cfg(target_os = "linux")]
+pub fn read() -> Result<SoftirqsMeasurement> {
+    os::read()
+}
+
+#[cfg(target_os = "linux")]
+mod os {
+    use std::io::{self, BufRead};
+    use std::path::Path;
+
+    use super::super::{file_to_buf_reader, parse_u64, path_to_string, precise_time_ns, Result};
+    use super::{Softirq, Softirqs, SoftirqsMeasurement};
+    use crate::error::ProbeError;
+
+    #[inline]
+    pub fn read() -> Result<SoftirqsMeasurement> {
+        read_and_parse_softirqs(Path::new("/proc/softirqs"))
+    }
+
+    #[inline]
+    pub fn read_and_parse_softirqs(path: &Path) -> Result<SoftirqsMeasurement> {
+        let reader = file_to_buf_reader(path)?;
+
+        let precise_time_ns = precise_time_ns();
+
+        let line_result: io::Result<Vec<String>> = reader.lines().collect();
+        let lines = line_result.map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
+
+        let cpu_count = match lines.first() {
+            Some(header) if header.trim_start().starts_with("CPU") => {
+                header.split_whitespace().count()
+            }
+            _ => {
+                return Err(ProbeError::UnexpectedContent(
+                    "CPU header not found".to_owned(),
+                ))
+            }
+        };
+
+        let mut softirqs = Softirqs::new();
+        for line in &lines[1..] {
+            let segments: Vec<&str> = line.split_whitespace().collect();
+            if segments.is_empty() {
+                continue;
+            }
+            let name = segments[0].trim_end_matches(':').to_owned();
+
+            if segments.len() != cpu_count + 1 {
+                return Err(ProbeError::UnexpectedContent(format!(
+                    "Expected {} CPUs, had {} for '{}'",
+                    cpu_count,
+                    segments.len() - 1,
+                    name
+                )));
+            }
+
+            let per_cpu = segments[1..]
+                .iter()
+                .map(|segment| parse_u64(segment))
+                .collect::<Result<Vec<u64>>>()?;
+
+            softirqs.insert(name, Softirq { per_cpu });
+        }
+
+        Ok(SoftirqsMeasurement {
+            precise_time_ns,
+            softirqs,
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::super::ProbeError;
+    use super::{Softirq, Softirqs, SoftirqsMeasurement};
+    use std::path::Path;
+
+    #[test]
+    fn test_read_softirqs() {
+        assert!(super::read().is_ok());
+        assert!(!super::read().unwrap().softirqs.is_empty());
+    }
+
+    #[test]
+    fn test_read_and_parse_softirqs() {
+        let path = Path::new("fixtures/linux/softirqs/proc_softirqs");
+        let measurement = super::os::read_and_parse_softirqs(path).unwrap();
+
+        assert!(measurement.precise_time_ns > 0);
+
+        let softirqs = measurement.softirqs;
+        assert_eq!(10, softirqs.len());
+
+        let net_rx = softirqs.get("NET_RX").unwrap();
+        assert_eq!(vec![907530, 1200, 800, 650], net_rx.per_cpu);
+        assert_eq!(910180, net_rx.total());
+
+        let timer = softirqs.get("TIMER").unwrap();
+        assert_eq!(vec![22595, 18000, 17500, 16999], timer.per_cpu);
+    }
+
+    #[test]
+    fn test_read_and_parse_softirqs_wrong_path() {
+        match super::os::read_and_parse_softirqs(Path::new("/nonsense")) {
+            Err(ProbeError::IO(_, _)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_softirqs_incomplete() {
+        let path = Path::new("fixtures/linux/softirqs/proc_softirqs_incomplete");
+        match super::os::read_and_parse_softirqs(path) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_softirqs_garbage() {
+        let path = Path::new("fixtures/linux/softirqs/proc_softirqs_garbage");
+        match super::os::read_and_parse_softirqs(path) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_full_minute() {
+        let measurement1 = helpers::measurement(60_000_000_000, vec![1000, 20]);
+        let measurement2 = helpers::measurement(120_000_000_000, vec![7000, 80]);
+
+        let per_minute = measurement1.calculate_per_minute(&measurement2).unwrap();
+        let net_rx = per_minute.softirqs.get("NET_RX").unwrap();
+        assert_eq!(vec![6000, 60], net_rx.per_cpu);
+        assert_eq!(6060, net_rx.total());
+    }
+
+    #[test]
+    fn test_calculate_per_minute_partial_minute() {
+        let measurement1 = helpers::measurement(60_000_000_000, vec![1000, 20]);
+        let measurement2 = helpers::measurement(90_000_000_000, vec![7000, 80]);
+
+        let per_minute = measurement1.calculate_per_minute(&measurement2).unwrap();
+        let net_rx = per_minute.softirqs.get("NET_RX").unwrap();
+        assert_eq!(vec![12000, 120], net_rx.per_cpu);
+    }
+
+    #[test]
+    fn test_calculate_per_minute_wrong_times() {
+        let measurement1 = helpers::measurement(90_000_000_000, vec![1000, 20]);
+        let measurement2 = helpers::measurement(60_000_000_000, vec![7000, 80]);
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::InvalidInput(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_values_lower() {
+        let measurement1 = helpers::measurement(60_000_000_000, vec![1000, 20]);
+        let measurement2 = helpers::measurement(120_000_000_000, vec![900, 80]);
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_different_softirqs() {
+        let measurement1 = helpers::measurement(60_000_000_000, vec![1000, 20]);
+        let measurement2 = SoftirqsMeasurement {
+            precise_time_ns: 120_000_000_000,
+            softirqs: Softirqs::new(),
+        };
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    mod helpers {
+        use super::{Softirq, Softirqs, SoftirqsMeasurement};
+
+        pub fn measurement(precise_time_ns: u64, per_cpu: Vec<u64>) -> SoftirqsMeasurement {
+            let mut softirqs = Softirqs::new();
+            softirqs.insert("NET_RX".to_owned(), Softirq { per_cpu });
+            SoftirqsMeasurement {
+                precise_time_ns,
+                softirqs,
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use super::Result;
+
+/// A swap partition or file from `/proc/swaps`. Sizes are in KB.
+#[derive(Debug, PartialEq)]
+pub struct SwapDevice {
+    pub filename: String,
+    pub swap_type: SwapType,
+    pub size: u64,
+    pub used: u64,
+    /// Devices with a higher priority are used first
+    pub priority: i64,
+}
+
+impl SwapDevice {
+    pub fn free(&self) -> u64 {
+        self.size.saturating_sub(self.used)
+    }
+
+    /// Whether the swap is backed by compressed memory instead of a disk
+    pub fn is_zram(&self) -> bool {
+        self.filename
+            .rsplit('/')
+            .next()
+            .map_or(false, |name| name.starts_with("zram"))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SwapType {
+    Partition,
+    File,
+    Other(String),
+}
+
+/// Read all swap partitions and files in use.
+#[cfg(target_os = "linux")]
+pub fn read() -> Result<Vec<SwapDevice>> {
+    os::read()
+}
+
+#[cfg(target_os = "linux")]
+mod os {
+    use std::io::BufRead;
+    use std::path::Path;
+
+    use super::super::{
+        file_to_buf_reader, parse_i64, parse_u64, path_to_string, unescape_octal, Result,
+    };
+    use super::{SwapDevice, SwapType};
+    use crate::error::ProbeError;
+
+    #[inline]
+    pub fn read() -> Result<Vec<SwapDevice>> {
+        read_and_parse_swaps(Path::new("/proc/swaps"))
+    }
+
+    #[inline]
+    pub fn read_and_parse_swaps(path: &Path) -> Result<Vec<SwapDevice>> {
+        let reader = file_to_buf_reader(path)?;
+
+        let mut out = Vec::new();
+        // The first line is a header
+        for line_result in reader.lines().skip(1) {
+            let line = line_result.map_err(|e| ProbeError::IO(e, path_to_string(path)))?;
+            let segments: Vec<&str> = line.split_whitespace().collect();
+            if segments.len() != 5 {
+                return Err(ProbeError::UnexpectedContent(format!(
+                    "Incorrect number of segments in '{}'",
+                    line
+                )));
+            }
+
+            let swap_type = match segments[1] {
+                "partition" => SwapType::Partition,
+                "file" => SwapType::File,
+                other => SwapType::Other(other.to_owned()),
+            };
+
+            out.push(SwapDevice {
+                filename: unescape_octal(segments[0]),
+                swap_type,
+                size: parse_u64(segments[2])?,
+                used: parse_u64(segments[3])?,
+                priority: parse_i64(segments[4])?,
+            });
+        }
+
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::super::ProbeError;
+    use super::{SwapDevice, SwapType};
+    use std::path::Path;
+
+    #[test]
+    fn test_read_swaps() {
+        assert!(super::read().is_ok());
+    }
+
+    #[test]
+    fn test_read_and_parse_swaps() {
+        let path = Path::new("fixtures/linux/swap/proc_swaps");
+        let devices = super::os::read_and_parse_swaps(path).unwrap();
+
+        let expected = vec![
+            SwapDevice {
+                filename: "/dev/sda2".to_owned(),
+                swap_type: SwapType::Partition,
+                size: 2097148,
+                used: 524288,
+                priority: -2,
+            },
+            SwapDevice {
+                filename: "/swap file".to_owned(),
+                swap_type: SwapType::File,
+                size: 1048572,
+                used: 0,
+                priority: -3,
+            },
+            SwapDevice {
+                filename: "/dev/zram0".to_owned(),
+                swap_type: SwapType::Partition,
+                size: 4194300,
+                used: 123456,
+                priority: 100,
+            },
+        ];
+        assert_eq!(expected, devices);
+
+        assert_eq!(1572860, devices[0].free());
+        assert!(!devices[0].is_zram());
+        assert!(!devices[1].is_zram());
+        assert!(devices[2].is_zram());
+    }
+
+    #[test]
+    fn test_read_and_parse_swaps_empty() {
+        let path = Path::new("fixtures/linux/swap/proc_swaps_empty");
+        let devices = super::os::read_and_parse_swaps(path).unwrap();
+        assert!(devices.is_empty());
+    }
+
+    #[test]
+    fn test_read_and_parse_swaps_wrong_path() {
+        match super::os::read_and_parse_swaps(Path::new("/nonsense")) {
+            Err(ProbeError::IO(_, _)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_swaps_incomplete() {
+        let path = Path::new("fixtures/linux/swap/proc_swaps_incomplete");
+        match super::os::read_and_parse_swaps(path) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_swaps_garbage() {
+        let path = Path::new("fixtures/linux/swap/proc_swaps_garbage");
+        match super::os::read_and_parse_swaps(path) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,342 @@
+use super::{
+    calculate_time_difference, per_minute_value, per_minute_value_option, FieldKind, Result,
+};
+use std::collections::HashMap;
+
+/// Measurement of the virtual memory statistics at a certain time.
+#[derive(Debug, PartialEq)]
+pub struct VmstatMeasurement {
+    pub precise_time_ns: u64,
+    pub stat: Vmstat,
+    /// Fields without a dedicated field in `Vmstat`, keyed by their name in `/proc/vmstat`. These
+    /// include gauges such as `nr_free_pages`.
+    pub other: HashMap<String, u64>,
+}
+
+impl VmstatMeasurement {
+    /// Calculate the virtual memory statistics per minute based on this measurement and a
+    /// measurement in the future. It is advisable to make the next measurement roughly a minute
+    /// from this one for the most reliable result.
+    pub fn calculate_per_minute(
+        &self,
+        next_measurement: &VmstatMeasurement,
+    ) -> Result<VmstatPerMinute> {
+        let time_difference =
+            calculate_time_difference(self.precise_time_ns, next_measurement.precise_time_ns)?;
+
+        let stat = &self.stat;
+        let next_stat = &next_measurement.stat;
+
+        Ok(VmstatPerMinute {
+            stat: Vmstat {
+                pgpgin: per_minute_value(
+                    "pgpgin",
+                    FieldKind::Counter,
+                    next_stat.pgpgin,
+                    stat.pgpgin,
+                    time_difference,
+                )?,
+                pgpgout: per_minute_value(
+                    "pgpgout",
+                    FieldKind::Counter,
+                    next_stat.pgpgout,
+                    stat.pgpgout,
+                    time_difference,
+                )?,
+                pswpin: per_minute_value(
+                    "pswpin",
+                    FieldKind::Counter,
+                    next_stat.pswpin,
+                    stat.pswpin,
+                    time_difference,
+                )?,
+                pswpout: per_minute_value(
+                    "pswpout",
+                    FieldKind::Counter,
+                    next_stat.pswpout,
+                    stat.pswpout,
+                    time_difference,
+                )?,
+                pgfault: per_minute_value(
+                    "pgfault",
+                    FieldKind::Counter,
+                    next_stat.pgfault,
+                    stat.pgfault,
+                    time_difference,
+                )?,
+                pgmajfault: per_minute_value(
+                    "pgmajfault",
+                    FieldKind::Counter,
+                    next_stat.pgmajfault,
+                    stat.pgmajfault,
+                    time_difference,
+                )?,
+                pgscan_kswapd: per_minute_value_option(
+                    "pgscan_kswapd",
+                    FieldKind::Counter,
+                    next_stat.pgscan_kswapd,
+                    stat.pgscan_kswapd,
+                    time_difference,
+                )?,
+                pgscan_direct: per_minute_value_option(
+                    "pgscan_direct",
+                    FieldKind::Counter,
+                    next_stat.pgscan_direct,
+                    stat.pgscan_direct,
+                    time_difference,
+                )?,
+                pgsteal_kswapd: per_minute_value_option(
+                    "pgsteal_kswapd",
+                    FieldKind::Counter,
+                    next_stat.pgsteal_kswapd,
+                    stat.pgsteal_kswapd,
+                    time_difference,
+                )?,
+                pgsteal_direct: per_minute_value_option(
+                    "pgsteal_direct",
+                    FieldKind::Counter,
+                    next_stat.pgsteal_direct,
+                    stat.pgsteal_direct,
+                    time_difference,
+                )?,
+                oom_kill: per_minute_value_option(
+                    "oom_kill",
+                    FieldKind::Counter,
+                    next_stat.oom_kill,
+                    stat.oom_kill,
+                    time_difference,
+                )?,
+            },
+        })
+    }
+}
+
+/// Paging, swapping and reclaim counters from `/proc/vmstat`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Vmstat {
+    /// KB paged in from disk
+    pub pgpgin: u64,
+    /// KB paged out to disk
+    pub pgpgout: u64,
+    /// Pages swapped in
+    pub pswpin: u64,
+    /// Pages swapped out
+    pub pswpout: u64,
+    pub pgfault: u64,
+    pub pgmajfault: u64,
+    /// Pages scanned by the background reclaim of kswapd, available since Linux 4.8
+    pub pgscan_kswapd: Option<u64>,
+    /// Pages scanned by direct reclaim while allocating memory, available since Linux 4.8
+    pub pgscan_direct: Option<u64>,
+    /// Pages reclaimed by kswapd, available since Linux 4.8
+    pub pgsteal_kswapd: Option<u64>,
+    /// Pages reclaimed by direct reclaim, available since Linux 4.8
+    pub pgsteal_direct: Option<u64>,
+    /// Processes killed by the OOM killer, available since Linux 4.13
+    pub oom_kill: Option<u64>,
+}
+
+/// Virtual memory statistics for a certain minute, calculated based on two measurements.
+#[derive(Debug, PartialEq)]
+pub struct VmstatPerMinute {
+    pub stat: Vmstat,
+}
+
+/// Read the current virtual memory statistics of the system.
+#[cfg(target_os = "linux")]
+pub fn read() -> Result<VmstatMeasurement> {
+    os::read()
+}
+
+#[cfg(target_os = "linux")]
+mod os {
+    use std::path::Path;
+
+    use super::super::{precise_time_ns, read_key_value_file, take_required_value, Result};
+    use super::{Vmstat, VmstatMeasurement};
+
+    #[inline]
+    pub fn read() -> Result<VmstatMeasurement> {
+        read_and_parse_vmstat(Path::new("/proc/vmstat"))
+    }
+
+    #[inline]
+    pub fn read_and_parse_vmstat(path: &Path) -> Result<VmstatMeasurement> {
+        let mut other = read_key_value_file(path)?;
+
+        let precise_time_ns = precise_time_ns();
+
+        let stat = Vmstat {
+            pgpgin: take_required_value(&mut other, "pgpgin")?,
+            pgpgout: take_required_value(&mut other, "pgpgout")?,
+            pswpin: take_required_value(&mut other, "pswpin")?,
+            pswpout: take_required_value(&mut other, "pswpout")?,
+            pgfault: take_required_value(&mut other, "pgfault")?,
+            pgmajfault: take_required_value(&mut other, "pgmajfault")?,
+            pgscan_kswapd: other.remove("pgscan_kswapd"),
+            pgscan_direct: other.remove("pgscan_direct"),
+            pgsteal_kswapd: other.remove("pgsteal_kswapd"),
+            pgsteal_direct: other.remove("pgsteal_direct"),
+            oom_kill: other.remove("oom_kill"),
+        };
+
+        Ok(VmstatMeasurement {
+            precise_time_ns,
+            stat,
+            other,
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use super::super::ProbeError;
+    use super::{Vmstat, VmstatMeasurement};
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    #[test]
+    fn test_read_vmstat() {
+        assert!(super::read().is_ok());
+    }
+
+    #[test]
+    fn test_read_and_parse_vmstat() {
+        let path = Path::new("fixtures/linux/vmstat/proc_vmstat");
+        let measurement = super::os::read_and_parse_vmstat(path).unwrap();
+
+        assert!(measurement.precise_time_ns > 0);
+
+        let expected = Vmstat {
+            pgpgin: 523298,
+            pgpgout: 2115488,
+            pswpin: 1200,
+            pswpout: 3400,
+            pgfault: 3726193,
+            pgmajfault: 245,
+            pgscan_kswapd: Some(90125),
+            pgscan_direct: Some(1408),
+            pgsteal_kswapd: Some(84211),
+            pgsteal_direct: Some(1022),
+            oom_kill: Some(2),
+        };
+        assert_eq!(expected, measurement.stat);
+
+        assert_eq!(Some(&850530), measurement.other.get("nr_free_pages"));
+        assert_eq!(Some(&12), measurement.other.get("allocstall_normal"));
+        assert_eq!(None, measurement.other.get("pgfault"));
+        assert_eq!(36, measurement.other.len());
+    }
+
+    #[test]
+    fn test_read_and_parse_vmstat_old_kernel() {
+        let path = Path::new("fixtures/linux/vmstat/proc_vmstat_old_kernel");
+        let measurement = super::os::read_and_parse_vmstat(path).unwrap();
+
+        assert_eq!(245, measurement.stat.pgmajfault);
+        assert_eq!(None, measurement.stat.pgscan_kswapd);
+        assert_eq!(None, measurement.stat.oom_kill);
+        assert_eq!(Some(&90125), measurement.other.get("pgscan_kswapd_normal"));
+    }
+
+    #[test]
+    fn test_read_and_parse_vmstat_wrong_path() {
+        match super::os::read_and_parse_vmstat(Path::new("/nonsense")) {
+            Err(ProbeError::IO(_, _)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_vmstat_incomplete() {
+        let path = Path::new("fixtures/linux/vmstat/proc_vmstat_incomplete");
+        match super::os::read_and_parse_vmstat(path) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_and_parse_vmstat_garbage() {
+        let path = Path::new("fixtures/linux/vmstat/proc_vmstat_garbage");
+        match super::os::read_and_parse_vmstat(path) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_full_minute() {
+        let measurement1 = helpers::measurement(60_000_000_000, 100, Some(10));
+        let measurement2 = helpers::measurement(120_000_000_000, 700, Some(40));
+
+        let per_minute = measurement1.calculate_per_minute(&measurement2).unwrap();
+        assert_eq!(600, per_minute.stat.pswpout);
+        assert_eq!(Some(30), per_minute.stat.pgscan_direct);
+        assert_eq!(None, per_minute.stat.oom_kill);
+    }
+
+    #[test]
+    fn test_calculate_per_minute_partial_minute() {
+        let measurement1 = helpers::measurement(60_000_000_000, 100, Some(10));
+        let measurement2 = helpers::measurement(90_000_000_000, 700, Some(40));
+
+        let per_minute = measurement1.calculate_per_minute(&measurement2).unwrap();
+        assert_eq!(1200, per_minute.stat.pswpout);
+        assert_eq!(Some(60), per_minute.stat.pgscan_direct);
+    }
+
+    #[test]
+    fn test_calculate_per_minute_wrong_times() {
+        let measurement1 = helpers::measurement(90_000_000_000, 100, Some(10));
+        let measurement2 = helpers::measurement(60_000_000_000, 700, Some(40));
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::InvalidInput(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_values_lower() {
+        let measurement1 = helpers::measurement(60_000_000_000, 700, Some(10));
+        let measurement2 = helpers::measurement(120_000_000_000, 100, Some(40));
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_calculate_per_minute_missing_field() {
+        let measurement1 = helpers::measurement(60_000_000_000, 100, Some(10));
+        let measurement2 = helpers::measurement(120_000_000_000, 700, None);
+
+        match measurement1.calculate_per_minute(&measurement2) {
+            Err(ProbeError::UnexpectedContent(_)) => (),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    mod helpers {
+        use super::{HashMap, Vmstat, VmstatMeasurement};
+
+        pub fn measurement(
+            precise_time_ns: u64,
+            pswpout: u64,
+            pgscan_direct: Option<u64>,
+        ) -> VmstatMeasurement {
+            VmstatMeasurement {
+                precise_time_ns,
+                stat: Vmstat {
+                    pswpout,
+                    pgscan_direct,
+                    ..Default::default()
+                },
+                other: HashMap::new(),
+            }
+        }
+    }
+}